@@ -0,0 +1,201 @@
+use crate::board::Board;
+use crate::eval::{PieceValues, Score};
+use crate::piece::{opposite_color, piece_color, piece_type, Color, Piece, BITS_KING};
+use crate::square::Square;
+
+/// Value used for kings in exchanges. A king may only take part in an exchange as the very last
+/// capturer, since it can't capture a defended piece.
+const KING_EXCHANGE_VALUE: Score = 100_000;
+
+/// List the pieces of `color` that the opponent can win material from by capturing, i.e. pieces
+/// that are attacked more times than they are defended, or attacked by a less valuable piece.
+///
+/// The outcome of capturing on each square is determined with a static exchange evaluation where
+/// both sides capture with their least valuable piece first and may stop capturing at any time.
+/// Pins and x-ray attacks are not considered. Kings are never listed.
+pub fn hanging_pieces(board: &Board, color: Color) -> Vec<(Square, Piece)> {
+    let values = PieceValues::CLASSIC;
+    let exchange_value = |p: Piece| {
+        if piece_type(p) == BITS_KING {
+            KING_EXCHANGE_VALUE
+        } else {
+            values.value(p)
+        }
+    };
+    let sorted_values = |pieces: Vec<(Square, Piece)>| {
+        let mut values = pieces
+            .into_iter()
+            .map(|(_, p)| exchange_value(p))
+            .collect::<Vec<_>>();
+        values.sort();
+        values
+    };
+
+    board
+        .pieces_iter()
+        .filter(|(_, p)| piece_color(*p) == color && piece_type(*p) != BITS_KING)
+        .filter(|(sq, p)| {
+            let attackers = sorted_values(board.attackers_of(sq, opposite_color(color)));
+            let defenders = sorted_values(board.attackers_of(sq, color));
+            static_exchange(values.value(*p), &attackers, &defenders) > 0
+        })
+        .collect()
+}
+
+/// Check if the side to move can force a repetition by giving check on every move, no matter how
+/// the opponent responds, within `max_plies` halfmoves. A line counts as a repetition as soon as a
+/// position from earlier in the line occurs again, so the defending side could avoid a threefold
+/// repetition only by deviating from it.
+///
+/// Lines that end in checkmate are not perpetual checks. This is a bounded search that grows
+/// exponentially with `max_plies`, so it is meant for short horizons.
+pub fn has_perpetual(board: &Board, max_plies: usize) -> bool {
+    let mut board = board.clone();
+    let mut line = vec![board.stable_hash()];
+
+    can_force_repetition(&mut board, max_plies, &mut line)
+}
+
+fn can_force_repetition(board: &mut Board, plies_left: usize, line: &mut Vec<u64>) -> bool {
+    if plies_left < 2 {
+        return false;
+    }
+
+    for mv in board.gen_moves() {
+        board
+            .move_piece(&mv.from, &mv.to, mv.promotion)
+            .expect("generated moves are legal");
+
+        // Only checks keep the opponent busy
+        let forced = board.in_check_to_move() && every_reply_repeats(board, plies_left - 1, line);
+
+        board.undo_move();
+        if forced {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn every_reply_repeats(board: &mut Board, plies_left: usize, line: &mut Vec<u64>) -> bool {
+    let replies = board.gen_moves();
+    if replies.is_empty() {
+        // Checkmate
+        return false;
+    }
+
+    replies.iter().all(|mv| {
+        board
+            .move_piece(&mv.from, &mv.to, mv.promotion)
+            .expect("generated moves are legal");
+
+        let hash = board.stable_hash();
+        let repeats = line.contains(&hash) || {
+            line.push(hash);
+            let forced = can_force_repetition(board, plies_left - 1, line);
+            line.pop();
+            forced
+        };
+
+        board.undo_move();
+        repeats
+    })
+}
+
+/// Material won by the side to capture on a square holding a piece worth `target`, where
+/// `attackers` and `defenders` are the values of the pieces of each side that can capture on the
+/// square, in the order they will capture.
+fn static_exchange(target: Score, attackers: &[Score], defenders: &[Score]) -> Score {
+    match attackers.split_first() {
+        // The capturer becomes the new target, and the defenders capture next
+        Some((&capturer, rest)) => (target - static_exchange(capturer, defenders, rest)).max(0),
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen;
+    use crate::piece::{BITS_BLACK, BITS_WHITE};
+    use crate::square::square;
+
+    fn hanging_squares(board: &Board, color: Color) -> Vec<Square> {
+        hanging_pieces(board, color)
+            .into_iter()
+            .map(|(sq, _)| sq)
+            .collect()
+    }
+
+    #[test]
+    fn perpetual_check() -> crate::Result<()> {
+        // Qh5+ Kg8 Qe8+ Kh7 repeats the position
+        let board = fen::import("4Q3/6pk/8/8/8/8/8/K7 w - - 0 1")?;
+        assert!(has_perpetual(&board, 4));
+        assert!(!has_perpetual(&board, 3));
+
+        // Without the pawn on g7, the king escapes the checks
+        let board = fen::import("4Q3/7k/8/8/8/8/8/K7 w - - 0 1")?;
+        assert!(!has_perpetual(&board, 6));
+
+        // Checkmate is not a perpetual check
+        let board = fen::import("7k/6pp/8/8/8/8/8/K3Q3 w - - 0 1")?;
+        assert!(!has_perpetual(&board, 4));
+
+        assert!(!has_perpetual(&Board::new(), 4));
+        Ok(())
+    }
+
+    #[test]
+    fn static_exchange_evaluation() {
+        assert_eq!(static_exchange(100, &[], &[]), 0);
+        assert_eq!(static_exchange(100, &[300], &[]), 100);
+        assert_eq!(static_exchange(100, &[300], &[100]), 0);
+        assert_eq!(static_exchange(500, &[100], &[100]), 400);
+        assert_eq!(static_exchange(100, &[100, 300], &[300]), 100);
+        assert_eq!(static_exchange(300, &[300], &[KING_EXCHANGE_VALUE]), 0);
+    }
+
+    #[test]
+    fn no_hanging_pieces_in_the_starting_position() {
+        let board = Board::new();
+        assert!(hanging_pieces(&board, BITS_WHITE).is_empty());
+        assert!(hanging_pieces(&board, BITS_BLACK).is_empty());
+    }
+
+    #[test]
+    fn undefended_pieces_are_hanging() -> crate::Result<()> {
+        // The knight on e5 is attacked by the rook on e8 and not defended
+        let board = fen::import("4r1k1/8/8/4N3/8/8/8/6K1 w - - 0 1")?;
+        assert_eq!(hanging_squares(&board, BITS_WHITE), vec![*square!("e5")]);
+        // The rook on e8 is attacked by nothing
+        assert!(hanging_pieces(&board, BITS_BLACK).is_empty());
+
+        // A defended knight is not hanging
+        let board = fen::import("4r1k1/8/8/4N3/3P4/8/8/6K1 w - - 0 1")?;
+        assert!(hanging_pieces(&board, BITS_WHITE).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn defended_pieces_attacked_by_cheaper_pieces_are_hanging() -> crate::Result<()> {
+        // The rook on d5 is defended by the pawn on e4, but attacked by the pawn on c6
+        let board = fen::import("6k1/8/2p5/3R4/4P3/8/8/6K1 b - - 0 1")?;
+        assert_eq!(hanging_squares(&board, BITS_WHITE), vec![*square!("d5")]);
+        Ok(())
+    }
+
+    #[test]
+    fn pieces_attacked_more_times_than_defended_are_hanging() -> crate::Result<()> {
+        // The pawn on d5 is attacked by the knights on c3 and f4, and only defended by the knight
+        // on e7
+        let board = fen::import("6k1/4n3/8/3p4/5N2/2N5/8/6K1 w - - 0 1")?;
+        assert_eq!(hanging_squares(&board, BITS_BLACK), vec![*square!("d5")]);
+
+        // With the king defending it as well, nothing is won
+        let board = fen::import("8/4n3/2k5/3p4/5N2/2N5/8/6K1 w - - 0 1")?;
+        assert!(hanging_pieces(&board, BITS_BLACK).is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use crate::square::Square;
+
+/// Set of squares, one bit per square. Bit 0 is A1, bit 7 is H1 and bit 63 is H8 (see
+/// `Square::index`).
+pub type Bitboard = u64;
+
+pub const EMPTY: Bitboard = 0;
+pub const FULL: Bitboard = !0;
+
+pub const FILE_A: Bitboard = 0x0101010101010101;
+pub const FILE_B: Bitboard = FILE_A << 1;
+pub const FILE_C: Bitboard = FILE_A << 2;
+pub const FILE_D: Bitboard = FILE_A << 3;
+pub const FILE_E: Bitboard = FILE_A << 4;
+pub const FILE_F: Bitboard = FILE_A << 5;
+pub const FILE_G: Bitboard = FILE_A << 6;
+pub const FILE_H: Bitboard = FILE_A << 7;
+/// All files, indexed by file index (0 for the A file)
+pub const FILES: [Bitboard; 8] = [
+    FILE_A, FILE_B, FILE_C, FILE_D, FILE_E, FILE_F, FILE_G, FILE_H,
+];
+
+pub const RANK_1: Bitboard = 0xff;
+pub const RANK_2: Bitboard = RANK_1 << 8;
+pub const RANK_3: Bitboard = RANK_1 << (2 * 8);
+pub const RANK_4: Bitboard = RANK_1 << (3 * 8);
+pub const RANK_5: Bitboard = RANK_1 << (4 * 8);
+pub const RANK_6: Bitboard = RANK_1 << (5 * 8);
+pub const RANK_7: Bitboard = RANK_1 << (6 * 8);
+pub const RANK_8: Bitboard = RANK_1 << (7 * 8);
+/// All ranks, indexed by rank index (0 for the first rank)
+pub const RANKS: [Bitboard; 8] = [
+    RANK_1, RANK_2, RANK_3, RANK_4, RANK_5, RANK_6, RANK_7, RANK_8,
+];
+
+/// D4, E4, D5 and E5
+pub const CENTER: Bitboard = (FILE_D | FILE_E) & (RANK_4 | RANK_5);
+/// The 16 squares from C3 to F6
+pub const EXTENDED_CENTER: Bitboard =
+    (FILE_C | FILE_D | FILE_E | FILE_F) & (RANK_3 | RANK_4 | RANK_5 | RANK_6);
+/// The E to H files
+pub const KINGSIDE: Bitboard = FILE_E | FILE_F | FILE_G | FILE_H;
+/// The A to D files
+pub const QUEENSIDE: Bitboard = FILE_A | FILE_B | FILE_C | FILE_D;
+
+pub const DARK_SQUARES: Bitboard = 0xaa55aa55aa55aa55;
+pub const LIGHT_SQUARES: Bitboard = !DARK_SQUARES;
+
+/// Squares attacked by a knight, indexed by the index of the knight's square (see
+/// `Square::index`).
+pub const KNIGHT_ATTACKS: [Bitboard; 64] = leaper_attacks(&[
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+]);
+
+/// Squares attacked by a king, indexed by the index of the king's square. Castling is not
+/// included.
+pub const KING_ATTACKS: [Bitboard; 64] = leaper_attacks(&[
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+]);
+
+/// Squares attacked by a white and by a black pawn, indexed by the index of the pawn's square.
+pub const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [
+    leaper_attacks(&[(-1, 1), (1, 1)]),
+    leaper_attacks(&[(-1, -1), (1, -1)]),
+];
+
+pub fn from_square(sq: &Square) -> Bitboard {
+    1 << sq.index()
+}
+
+pub fn contains(bb: Bitboard, sq: &Square) -> bool {
+    bb & from_square(sq) != EMPTY
+}
+
+/// Iterate over the squares in a bitboard, from A1 towards H8.
+pub fn squares(mut bb: Bitboard) -> impl Iterator<Item = Square> {
+    std::iter::from_fn(move || {
+        if bb == EMPTY {
+            return None;
+        }
+
+        let idx = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        Some(Square::from_index(idx))
+    })
+}
+
+/// Attack table of a piece that jumps by the file and rank steps in `steps`.
+const fn leaper_attacks(steps: &[(i32, i32)]) -> [Bitboard; 64] {
+    let mut res = [EMPTY; 64];
+
+    let mut idx = 0;
+    while idx < 64 {
+        let mut i = 0;
+        while i < steps.len() {
+            let file = (idx % 8) as i32 + steps[i].0;
+            let rank = (idx / 8) as i32 + steps[i].1;
+            if file >= 0 && file < 8 && rank >= 0 && rank < 8 {
+                res[idx] |= 1 << (rank * 8 + file);
+            }
+            i += 1;
+        }
+        idx += 1;
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::square;
+
+    #[test]
+    fn square_colors_agree_with_masks() {
+        for idx in 0..64 {
+            let sq = Square::from_index(idx);
+            assert_eq!(sq.is_dark(), contains(DARK_SQUARES, &sq));
+            assert_eq!(!sq.is_dark(), contains(LIGHT_SQUARES, &sq));
+        }
+        assert!(square!("a1").is_dark());
+        assert!(!square!("h1").is_dark());
+    }
+
+    #[test]
+    fn files_and_ranks() {
+        assert!(contains(FILE_E, square!("e7")));
+        assert!(!contains(FILE_E, square!("d7")));
+        assert!(contains(RANKS[6], square!("e7")));
+        assert_eq!((FILES[3] & RANKS[3]).count_ones(), 1);
+        assert_eq!(KINGSIDE | QUEENSIDE, FULL);
+        assert_eq!(KINGSIDE & QUEENSIDE, EMPTY);
+    }
+
+    #[test]
+    fn leaper_attacks() {
+        let squares = |bb| squares(bb).map(|sq| sq.to_str()).collect::<Vec<_>>();
+
+        assert_eq!(
+            squares(KNIGHT_ATTACKS[square!("g1").index()]),
+            vec!["E2", "F3", "H3"]
+        );
+        assert_eq!(KNIGHT_ATTACKS[square!("d4").index()].count_ones(), 8);
+        assert_eq!(
+            squares(KING_ATTACKS[square!("a8").index()]),
+            vec!["A7", "B7", "B8"]
+        );
+        assert_eq!(KING_ATTACKS[square!("e4").index()].count_ones(), 8);
+        assert_eq!(
+            squares(PAWN_ATTACKS[0][square!("e4").index()]),
+            vec!["D5", "F5"]
+        );
+        assert_eq!(squares(PAWN_ATTACKS[1][square!("a7").index()]), vec!["B6"]);
+    }
+
+    #[test]
+    fn center() {
+        let center = squares(CENTER).collect::<Vec<_>>();
+        assert_eq!(
+            center,
+            vec![
+                *square!("d4"),
+                *square!("e4"),
+                *square!("d5"),
+                *square!("e5")
+            ]
+        );
+        assert_eq!(EXTENDED_CENTER.count_ones(), 16);
+        assert_eq!(EXTENDED_CENTER & CENTER, CENTER);
+    }
+}
@@ -1,440 +1,1648 @@
 use itertools::Itertools;
 
+use crate::bitboard::{self, Bitboard};
 use crate::error::chess_error;
+use crate::eval::Material;
 use crate::fen;
-use crate::internal::utils::clamp_board_idx;
+use crate::internal::utils::fnv1a_64;
+use crate::magic;
+use crate::move_list::MoveList;
 use crate::piece::{
-    is_piece, piece_color, piece_type, Color, Piece, BITS_BISHOP, BITS_BLACK, BITS_KING,
-    BITS_KNIGHT, BITS_NO_PIECE, BITS_PAWN, BITS_QUEEN, BITS_ROOK, BITS_WHITE,
+    has_moved, is_piece, opposite_color, piece_color, piece_type, unicode_symbol, Color, Piece,
+    BITS_BISHOP, BITS_BLACK, BITS_HAS_MOVED, BITS_KING, BITS_KNIGHT, BITS_NO_PIECE, BITS_PAWN,
+    BITS_QUEEN, BITS_ROOK, BITS_WHITE,
 };
+use crate::positions;
 use crate::square::Square;
+use crate::zobrist;
 use crate::Result;
 
-pub type Move = (Square, Square);
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// A move from one square to another.
+///
+/// Moves generated by `Board` are annotated with the moved piece, the captured piece and flags
+/// describing special moves. Moves created with `Move::new` or `Move::with_promotion` carry no
+/// annotations. The annotations follow from the position the move is played in, so they don't
+/// take part in comparisons: two moves are equal if they have the same squares and promotion.
+#[derive(Debug, Copy, Clone)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    /// The piece type a pawn is promoted to. This is one of `PROMOTION_PIECES` for promotions and
+    /// None for all other moves.
+    pub promotion: Option<Piece>,
+    /// The moved piece as it was before the move, or `BITS_NO_PIECE` if the move isn't annotated
+    pub moved: Piece,
+    /// The captured piece, or `BITS_NO_PIECE` if the move isn't a capture or isn't annotated
+    pub captured: Piece,
+    pub flags: MoveFlags,
+}
+
+/// The piece types a pawn may be promoted to.
+pub const PROMOTION_PIECES: [Piece; 4] = [BITS_QUEEN, BITS_ROOK, BITS_BISHOP, BITS_KNIGHT];
+
+/// The promotions generated by `Board::gen_moves_into` and `Board::gen_pseudo_legal_moves_into`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Promotions {
+    /// One move for each of `PROMOTION_PIECES`.
+    All,
+    /// Only promotions to a queen, for engines and UIs that leave out underpromotions.
+    QueenOnly,
+}
+
+impl Promotions {
+    fn pieces(self) -> &'static [Piece] {
+        match self {
+            Promotions::All => &PROMOTION_PIECES,
+            Promotions::QueenOnly => &[BITS_QUEEN],
+        }
+    }
+}
+
+/// Bit field type for flagging special moves.
+///
+/// Bit 0: Castling (the king's move of two squares)
+/// Bit 1: En passant capture
+/// Bit 2: Double step of a pawn
+pub type MoveFlags = u8;
+
+pub const MOVE_FLAGS_NONE: MoveFlags = 0;
+pub const MOVE_CASTLING: MoveFlags = 1 << 0;
+pub const MOVE_EN_PASSANT: MoveFlags = 1 << 1;
+pub const MOVE_DOUBLE_STEP: MoveFlags = 1 << 2;
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Self {
+        Move {
+            from,
+            to,
+            promotion: None,
+            moved: BITS_NO_PIECE,
+            captured: BITS_NO_PIECE,
+            flags: MOVE_FLAGS_NONE,
+        }
+    }
+
+    pub fn with_promotion(from: Square, to: Square, promotion: Piece) -> Self {
+        Move {
+            promotion: Some(piece_type(promotion)),
+            ..Move::new(from, to)
+        }
+    }
+
+    pub fn is_capture(&self) -> bool {
+        is_piece(self.captured)
+    }
+
+    pub fn is_promotion(&self) -> bool {
+        self.promotion.is_some()
+    }
+
+    pub fn is_castling(&self) -> bool {
+        self.flags & MOVE_CASTLING != MOVE_FLAGS_NONE
+    }
+
+    pub fn is_en_passant(&self) -> bool {
+        self.flags & MOVE_EN_PASSANT != MOVE_FLAGS_NONE
+    }
+
+    pub fn is_double_step(&self) -> bool {
+        self.flags & MOVE_DOUBLE_STEP != MOVE_FLAGS_NONE
+    }
+}
+
+impl PartialEq for Move {
+    fn eq(&self, other: &Self) -> bool {
+        self.from == other.from && self.to == other.to && self.promotion == other.promotion
+    }
+}
+
+impl Eq for Move {}
+
+/// Bit field type for representing which castling moves are still available.
+///
+/// Bit 0: White may castle kingside
+/// Bit 1: White may castle queenside
+/// Bit 2: Black may castle kingside
+/// Bit 3: Black may castle queenside
+pub type CastlingRights = u8;
+
+pub const CASTLE_NONE: CastlingRights = 0;
+pub const CASTLE_WHITE_KINGSIDE: CastlingRights = 1 << 0;
+pub const CASTLE_WHITE_QUEENSIDE: CastlingRights = 1 << 1;
+pub const CASTLE_BLACK_KINGSIDE: CastlingRights = 1 << 2;
+pub const CASTLE_BLACK_QUEENSIDE: CastlingRights = 1 << 3;
+
+/// Game phase, as estimated from the material left on the board.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+/// Whether the game is over in a position, and why. See `Board::status`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum GameStatus {
+    Ongoing,
+    /// The side to move is checkmated. The color is the winner.
+    Checkmate(Color),
+    Stalemate,
+    DrawByInsufficientMaterial,
+    DrawByFiftyMove,
+    DrawByThreefoldRepetition,
+}
 
+/// Length of the longest encoding written by `Board::pack`: the occupancy and a 4-bit code for
+/// each of the 64 squares.
+const PACKED_MAX_LEN: usize = 8 + 32;
+
+/// Phase value of the starting position. See `Board::phase_value`.
+pub const PHASE_VALUE_MAX: u32 = 24;
+
+/// A chess position along with the moves applied to it with `move_piece`.
+///
+/// `Board` is not `Copy`, since it owns the move history used for `undo_move` and repetitions.
+/// Cloning a board with a history allocates for it. Code that copies positions in a loop, such as
+/// a search, should use `Board::without_history`, which never allocates, or make and take back
+/// moves with `make_move` and `unmake_move`.
 #[derive(Clone)]
 pub struct Board {
-    pub pieces: Box<[[Piece; 8]; 8]>,
+    /// The pieces, indexed by file and rank. Changes must go through `Board::put` (or be followed
+    /// by `Board::track_pieces`) to keep the tracked state below up to date.
+    pub(crate) pieces: [[Piece; 8]; 8],
     pub side_to_move: Color,
-    /// This is set to the square that a pawn can be captured on in case it can be captured via en passant.
-    /// If en passant is not possible, this is set to None. The color is set to the color of the pawn.
-    /// This struct member is reset/cleared after each move.
+    /// This is set to the square that a pawn skipped over with a double step in the last move, i.e.
+    /// the square an enemy pawn would move to when capturing it en passant. As in FEN, it is set
+    /// after every double step, whether or not an en passant capture is possible. Otherwise, this
+    /// is None.
     pub en_passant: Option<Square>,
+    /// Number of halfmoves since the last capture or pawn move, used for the fifty-move rule.
+    pub halfmove_clock: u32,
+    /// Moves applied with `move_piece`, along with what is needed to take them back.
+    pub(crate) history: Vec<HistoryEntry>,
+    /// Zobrist hashes (see `Board::zobrist_hash`) of the positions before each move in `history`.
+    pub(crate) position_hashes: Vec<u64>,
+    /// Squares of the white and the black king (see `color_index`).
+    pub(crate) king_squares: [Option<Square>; 2],
+    /// Squares of the white and the black pieces (see `color_index`), so that move generation only
+    /// visits the squares of the side to move.
+    pub(crate) occupancy: [Bitboard; 2],
+    /// XOR of the Zobrist keys of the pieces on the board (see `zobrist::piece_key`).
+    pub(crate) piece_hash: u64,
+    /// Squares attacked by the piece on each square, indexed by `Square::index`. Empty squares
+    /// attack nothing.
+    pub(crate) piece_attacks: [Bitboard; 64],
 }
 
-impl Board {
-    pub fn new() -> Self {
-        fen::import("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap()
+/// Index of `color` in arrays with one entry per color, white first.
+pub(crate) fn color_index(color: Color) -> usize {
+    (color >> 3) as usize
+}
+
+/// Token returned by `Board::make_move` that holds what is needed to take the move back with
+/// `Board::unmake_move`. It can't be copied, so each move can only be taken back once.
+///
+/// The token also tells what the move did and what the state of the board was before it.
+pub struct Undo {
+    entry: HistoryEntry,
+    castling_rights: CastlingRights,
+}
+
+impl Undo {
+    /// The move that this token takes back.
+    pub fn mv(&self) -> Move {
+        self.entry.move_
     }
 
-    pub fn side_to_move(&self) -> Color {
-        self.side_to_move
+    /// The moved piece as it was before the move, i.e. a pawn for promotions.
+    pub fn moved(&self) -> Piece {
+        self.entry.moved
     }
 
-    pub fn get_piece(&self, sq: &Square) -> Piece {
-        self.pieces[sq.0][sq.1]
+    /// The captured piece, or None if the move wasn't a capture.
+    pub fn captured(&self) -> Option<Piece> {
+        is_piece(self.entry.captured).then_some(self.entry.captured)
     }
 
-    pub fn gen_moves(&mut self) -> Vec<Move> {
-        let mut res = Vec::new();
+    /// The square the captured piece stood on, or None if the move wasn't a capture. This differs
+    /// from the target square of the move for en passant captures.
+    pub fn captured_square(&self) -> Option<Square> {
+        is_piece(self.entry.captured).then_some(self.entry.captured_sq)
+    }
 
-        for (rank, file) in (0..8).cartesian_product(0..8) {
-            let from = Square(file, rank);
-            let piece = self.pieces[file][rank];
-            if is_piece(piece) && piece_color(piece) == self.side_to_move() {
-                match piece_type(piece) {
-                    BITS_KING => res.append(&mut self.gen_king_moves(&from)),
-                    BITS_PAWN => res.append(&mut self.gen_pawn_moves(&from)),
-                    BITS_ROOK => res.append(&mut self.gen_rook_moves(&from)),
-                    BITS_KNIGHT => res.append(&mut self.gen_knight_moves(&from)),
-                    BITS_BISHOP => res.append(&mut self.gen_bishop_moves(&from)),
-                    BITS_QUEEN => res.append(&mut self.gen_queen_moves(&from)),
-                    p => panic!("Piece type {p} Not implemented yet"),
-                }
-            }
-        }
+    /// The en passant square before the move.
+    pub fn en_passant(&self) -> Option<Square> {
+        self.entry.en_passant
+    }
 
-        res.into_iter()
-            .filter(|mv| !self.move_cause_self_check(*mv))
-            .collect_vec()
+    /// The castling rights before the move.
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling_rights
     }
 
-    pub fn move_piece(&mut self, from: &Square, to: &Square) -> Result<()> {
-        let possible_moves = self.gen_moves();
+    /// The halfmove clock before the move.
+    pub fn halfmove_clock(&self) -> u32 {
+        self.entry.halfmove_clock
+    }
+}
 
-        let move_ = (*from, *to);
+/// Token returned by `Board::make_null_move` for taking the null move back with
+/// `Board::undo_null_move`.
+pub struct NullMoveUndo {
+    en_passant: Option<Square>,
+}
 
-        if possible_moves.contains(&move_) {
-            Ok(())
-        } else {
-            Err(chess_error("Not a valid move"))
-        }
+/// What is needed to take back a move, besides the move itself.
+#[derive(Clone)]
+pub(crate) struct HistoryEntry {
+    pub move_: Move,
+    /// The moved piece as it was before the move (i.e. a pawn for promotions)
+    pub moved: Piece,
+    /// The captured piece, or `BITS_NO_PIECE` if the move wasn't a capture
+    pub captured: Piece,
+    /// Where the captured piece stood. This differs from the move's target square for en passant.
+    pub captured_sq: Square,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u32,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    fn gen_king_moves(&self, from: &Square) -> Vec<Move> {
-        assert_eq!(piece_type(self.pieces[from.0][from.1]), BITS_KING);
+/// Boards are equal when their positions are: the pieces, the side to move, the castling rights
+/// and the en passant square are the same. The halfmove clock and the move history are not
+/// compared, so equal boards are repetitions of each other in the sense of
+/// `Board::is_threefold_repetition`.
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        let placement = |board: &Board| board.pieces.map(|file| file.map(|p| p & !BITS_HAS_MOVED));
+
+        self.side_to_move == other.side_to_move
+            && self.legal_en_passant() == other.legal_en_passant()
+            && self.castling_rights() == other.castling_rights()
+            && placement(self) == placement(other)
+    }
+}
 
-        let mut res = Vec::new();
+impl Eq for Board {}
 
-        for file in clamp_board_idx(from.0 as i32 - 1)..(clamp_board_idx(from.0 as i32 + 1) + 1) {
-            for rank in clamp_board_idx(from.1 as i32 - 1)..(clamp_board_idx(from.1 as i32 + 1) + 1)
-            {
-                if from.0 == file && from.1 == rank {
-                    // The king's own position
-                    continue;
-                }
+/// Consistent with the `PartialEq` implementation; only the position is hashed. The pieces are
+/// hashed through their tracked Zobrist keys, which don't depend on whether a piece has moved.
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.piece_hash.hash(state);
+        self.side_to_move.hash(state);
+        self.legal_en_passant().map(|sq| sq.index()).hash(state);
+        self.castling_rights().hash(state);
+    }
+}
 
-                let king_col: Color = piece_color(self.pieces[from.0][from.1]);
+/// The diagram of `Board::to_ascii`, followed by the FEN string of the position:
+///
+/// ```text
+/// 8 . . . . k . . .
+/// 7 . . . . . . . .
+/// 6 . . . . . . . .
+/// 5 . . . . . . . .
+/// 4 . . . . P . . .
+/// 3 . . . . . . . .
+/// 2 . . . . . . . .
+/// 1 . . . . K . . .
+///   a b c d e f g h
+/// 4k3/8/8/8/4P3/8/8/4K3 b - e3 0 0
+/// ```
+///
+/// The same format is accepted by `Board::try_from`, so the output of a failing test can be pasted
+/// into a new test case.
+impl fmt::Debug for Board {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.to_ascii(), fen::export(self))
+    }
+}
 
-                let p = self.get_piece_unbounded(file as i32, rank as i32);
-                if is_piece(p) && piece_color(p) == king_col {
-                    continue;
-                }
+/// Parse the format written by the `Debug` implementation of `Board`. The position is read from
+/// the FEN string on the last line, and the diagram above it must show the same pieces. Leading and
+/// trailing whitespace on each line is ignored, as are empty lines.
+impl TryFrom<&str> for Board {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(s: &str) -> Result<Self> {
+        let lines: Vec<&str> = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let Some((fen_line, diagram)) = lines.split_last() else {
+            return Err(chess_error("Empty board diagram"));
+        };
 
-                res.push((*from, Square(file, rank)));
+        let board = fen::import(fen_line)?;
+        let expected = format!("{board:?}");
+        let expected_diagram: Vec<&str> = expected.lines().map(str::trim).collect();
+        if diagram.len() != expected_diagram.len() - 1 {
+            return Err(chess_error(&format!(
+                "Expected {} diagram lines above the FEN string, found {}",
+                expected_diagram.len() - 1,
+                diagram.len()
+            )));
+        }
+        for (line, expected_line) in diagram.iter().zip(expected_diagram) {
+            if *line != expected_line {
+                return Err(chess_error(&format!(
+                    "Diagram line \"{line}\" doesn't match the FEN string (expected \"{expected_line}\")"
+                )));
             }
         }
 
-        res
+        Ok(board)
     }
+}
 
-    fn gen_pawn_moves(&self, from: &Square) -> Vec<Move> {
-        let file = from.0;
-        let rank = from.1;
-        let piece = self.pieces[file][rank];
-        let facing_dir: i32 = if self.side_to_move() == BITS_WHITE {
-            1
-        } else {
-            -1
-        };
+impl Board {
+    pub fn new() -> Self {
+        positions::startpos()
+    }
 
-        assert_eq!(piece_type(self.pieces[from.0][from.1]), BITS_PAWN);
-        assert_eq!(
-            piece_color(self.pieces[from.0][from.1]),
-            self.side_to_move()
-        );
-        assert!(rank > 0);
-        assert!(rank < 7);
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
 
-        let mut res = Vec::new();
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
 
-        // Move forward one step
-        let rank_dest = (rank as i32 + facing_dir) as usize;
-        if self.pieces[file][rank_dest] == BITS_NO_PIECE {
-            res.push((*from, Square(file, rank_dest)));
+    pub fn get_piece(&self, sq: &Square) -> Piece {
+        self.pieces[sq.0][sq.1]
+    }
 
-            // Move forward two steps
-            let rank_dest = (rank as i32 + 2 * facing_dir) as usize;
-            if ((rank == 1 && piece_color(piece) == BITS_WHITE)
-                && (rank == 6 && piece_color(piece) == BITS_BLACK))
-                && self.pieces[file][rank_dest] == BITS_NO_PIECE
-            {
-                res.push((*from, Square(file, rank_dest)));
+    /// Put `piece` on `sq`, replacing the piece there if any. `BITS_NO_PIECE` empties the square.
+    ///
+    /// This is meant for setting up positions; the move history, castling rights and en passant
+    /// square are left as they are.
+    pub fn set_piece(&mut self, sq: &Square, piece: Piece) {
+        self.put(*sq, piece);
+    }
+
+    /// Iterate over the occupied squares and their pieces, from a1 to h8 rank by rank (i.e. in
+    /// `Square::index` order).
+    pub fn pieces_iter(&self) -> impl Iterator<Item = (Square, Piece)> + '_ {
+        bitboard::squares(self.occupancy[0] | self.occupancy[1]).map(|sq| (sq, self.get_piece(&sq)))
+    }
+
+    /// Text diagram of the board with rank 8 at the top and labels for the ranks and files. Pieces
+    /// are shown with their FEN letters and empty squares with dots. Every line, including the
+    /// last, ends with a newline.
+    pub fn to_ascii(&self) -> String {
+        self.diagram(BITS_WHITE, fen::piece_to_letter, '.')
+    }
+
+    /// Like `to_ascii`, but with Unicode chess symbols (see `piece::unicode_symbol`), seen from the
+    /// side of `perspective`. From Black's perspective, rank 1 is at the top and the h-file to the
+    /// left.
+    pub fn to_unicode(&self, perspective: Color) -> String {
+        self.diagram(perspective, unicode_symbol, '·')
+    }
+
+    /// The position mirrored horizontally, i.e. with the a-file and the h-file swapped. Castling
+    /// rights are dropped, since the kings and rooks are no longer on their castling squares. The
+    /// move history is not kept.
+    pub fn mirror(&self) -> Board {
+        let mut mirrored = self.without_history();
+        for (file, rank) in (0..8).cartesian_product(0..8) {
+            let mut piece = self.pieces[file][rank];
+            if piece_type(piece) == BITS_KING || piece_type(piece) == BITS_ROOK {
+                piece |= BITS_HAS_MOVED;
             }
+            mirrored.pieces[7 - file][rank] = piece;
         }
+        mirrored.en_passant = self.en_passant.map(|sq| Square(7 - sq.0, sq.1));
+        mirrored.track_pieces();
 
-        // Capture right
-        if file < 7 {
-            let dest = self.pieces[file + 1][rank_dest];
-            if is_piece(dest) && piece_color(piece) != piece_color(dest) {
-                res.push((*from, Square(file + 1, rank_dest)));
-            } else if self
-                .en_passant
-                .map_or(false, |sq| Square(file + 1, rank_dest) == sq)
-            {
-                res.push((*from, Square(file + 1, rank_dest)));
-            }
+        mirrored
+    }
+
+    /// The position flipped vertically with the colors of the pieces and the side to move
+    /// swapped, so that pawns still move forward. The result is the same position with the roles
+    /// of White and Black reversed; castling rights and the en passant square follow the pieces.
+    /// The move history is not kept.
+    pub fn flip_vertical(&self) -> Board {
+        let mut flipped = self.without_history();
+        for (file, rank) in (0..8).cartesian_product(0..8) {
+            let piece = self.pieces[file][rank];
+            flipped.pieces[file][7 - rank] = if is_piece(piece) {
+                piece ^ BITS_BLACK
+            } else {
+                piece
+            };
         }
+        flipped.en_passant = self.en_passant.map(|sq| Square(sq.0, 7 - sq.1));
+        flipped.side_to_move = opposite_color(self.side_to_move);
+        flipped.track_pieces();
 
-        // Capture left
-        if file > 0 {
-            let dest = self.pieces[file - 1][rank_dest];
-            if is_piece(dest) && piece_color(piece) != piece_color(dest) {
-                res.push((*from, Square(file - 1, rank_dest)));
-            } else if self
-                .en_passant
-                .map_or(false, |sq| Square(file - 1, rank_dest) == sq)
-            {
-                res.push((*from, Square(file - 1, rank_dest)));
+        flipped
+    }
+
+    /// Derive the castling rights from the has-moved bits of the kings and rooks.
+    ///
+    /// A right is available as long as both the king and the corresponding rook stand unmoved
+    /// on their home squares.
+    pub fn castling_rights(&self) -> CastlingRights {
+        let unmoved = |sq: Square, p_type: Piece, color: Color| {
+            let p = self.get_piece(&sq);
+            piece_type(p) == p_type && piece_color(p) == color && !has_moved(p)
+        };
+
+        let mut rights = CASTLE_NONE;
+        for (color, rank, kingside, queenside) in [
+            (BITS_WHITE, 0, CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE),
+            (BITS_BLACK, 7, CASTLE_BLACK_KINGSIDE, CASTLE_BLACK_QUEENSIDE),
+        ] {
+            if !unmoved(Square(4, rank), BITS_KING, color) {
+                continue;
+            }
+            if unmoved(Square(7, rank), BITS_ROOK, color) {
+                rights |= kingside;
+            }
+            if unmoved(Square(0, rank), BITS_ROOK, color) {
+                rights |= queenside;
             }
         }
 
-        res
+        rights
     }
 
-    fn gen_bishop_moves(&self, &from: &Square) -> Vec<Move> {
-        assert_eq!(
-            piece_color(self.pieces[from.0][from.1]),
-            self.side_to_move()
-        );
+    /// Weighted count of the non-pawn material on the board, where minor pieces count as 1, rooks
+    /// as 2 and queens as 4. This is `PHASE_VALUE_MAX` in the starting position and 0 when only
+    /// kings and pawns remain. The value is capped at `PHASE_VALUE_MAX` in case of promotions.
+    pub fn phase_value(&self) -> u32 {
+        let value: u32 = self
+            .pieces_iter()
+            .map(|(_, p)| match piece_type(p) {
+                BITS_KNIGHT | BITS_BISHOP => 1,
+                BITS_ROOK => 2,
+                BITS_QUEEN => 4,
+                _ => 0,
+            })
+            .sum();
+
+        value.min(PHASE_VALUE_MAX)
+    }
 
-        let mut res = Vec::new();
+    pub fn phase(&self) -> Phase {
+        match self.phase_value() {
+            22.. => Phase::Opening,
+            9..=21 => Phase::Middlegame,
+            _ => Phase::Endgame,
+        }
+    }
 
-        // Walk along the diagonal directions
-        res.append(&mut self.straight_path(&from, 1, 1));
-        res.append(&mut self.straight_path(&from, 1, -1));
-        res.append(&mut self.straight_path(&from, -1, -1));
-        res.append(&mut self.straight_path(&from, -1, 1));
+    /// Count the pieces of `color`. Use `Material::value` to get their total value.
+    pub fn material(&self, color: Color) -> Material {
+        let mut material = Material::default();
+        for (_, p) in self.pieces_iter().filter(|(_, p)| piece_color(*p) == color) {
+            match piece_type(p) {
+                BITS_PAWN => material.pawns += 1,
+                BITS_KNIGHT => material.knights += 1,
+                BITS_BISHOP => material.bishops += 1,
+                BITS_ROOK => material.rooks += 1,
+                BITS_QUEEN => material.queens += 1,
+                _ => {}
+            }
+        }
 
-        res.iter().map(|dest| (from, *dest)).collect_vec()
+        material
     }
 
-    fn gen_rook_moves(&self, &from: &Square) -> Vec<Move> {
-        assert_eq!(
-            piece_color(self.pieces[from.0][from.1]),
-            self.side_to_move()
-        );
+    /// The material on the board written as e.g. "KQRRvKQR", with White's pieces first. Pieces are
+    /// listed in the order K, Q, R, B, N, P.
+    pub fn material_signature(&self) -> String {
+        let side = |color: Color| -> String {
+            [
+                BITS_KING,
+                BITS_QUEEN,
+                BITS_ROOK,
+                BITS_BISHOP,
+                BITS_KNIGHT,
+                BITS_PAWN,
+            ]
+            .iter()
+            .flat_map(|p_type| {
+                let count = self
+                    .pieces_iter()
+                    .filter(|(_, p)| piece_type(*p) == *p_type && piece_color(*p) == color)
+                    .count();
+                std::iter::repeat_n(fen::piece_to_letter(BITS_WHITE | p_type), count)
+            })
+            .collect()
+        };
 
-        let mut res = Vec::new();
+        format!("{}v{}", side(BITS_WHITE), side(BITS_BLACK))
+    }
 
-        // Walk along the orthogonal directions
-        res.append(&mut self.straight_path(&from, 1, 0));
-        res.append(&mut self.straight_path(&from, -1, 0));
-        res.append(&mut self.straight_path(&from, 0, 1));
-        res.append(&mut self.straight_path(&from, 0, -1));
+    /// Deterministic hash of the position that does not depend on any random tables. The value is
+    /// guaranteed to stay the same across versions of this crate, so it is suitable for durable
+    /// database keys.
+    ///
+    /// The hash is the 64-bit FNV-1a hash of the packed encoding produced by `Board::pack`.
+    pub fn stable_hash(&self) -> u64 {
+        let mut buf = [0; PACKED_MAX_LEN];
+        let len = self.pack(&mut buf);
 
-        res.iter().map(|dest| (from, *dest)).collect_vec()
+        fnv1a_64(&buf[..len])
     }
 
-    fn gen_knight_moves(&self, &from: &Square) -> Vec<Move> {
-        let file = from.0;
-        let rank = from.1;
-        let piece = self.pieces[file][rank];
-        let knight_color = piece_color(piece);
+    /// Zobrist hash of the position, built from the keys in `zobrist`. It covers the same features
+    /// as `Board` equality: the pieces, the side to move, the castling rights and the en passant
+    /// square (see `Board::legal_en_passant`). Unlike `stable_hash`, it is fast to compute and
+    /// meant for in-memory tables.
+    ///
+    /// The keys of the pieces are kept combined as the pieces move, so apart from checking the en
+    /// passant captures, this takes constant time.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = self.piece_hash ^ zobrist::castling_key(self.castling_rights());
+        if let Some(sq) = self.legal_en_passant() {
+            hash ^= zobrist::en_passant_key(sq.0);
+        }
+        if self.side_to_move == BITS_BLACK {
+            hash ^= zobrist::BLACK_TO_MOVE;
+        }
 
-        assert_eq!(piece_type(self.pieces[from.0][from.1]), BITS_KNIGHT);
-        assert_eq!(
-            piece_color(self.pieces[from.0][from.1]),
-            self.side_to_move()
-        );
+        hash
+    }
 
-        let mut res = Vec::new();
+    /// The en passant square, if the side to move has a legal en passant capture. The en passant
+    /// square is set after every double step, but positions are only told apart by it when such a
+    /// capture exists, as for repetitions under the FIDE rules.
+    pub fn legal_en_passant(&self) -> Option<Square> {
+        let ep = self.en_passant?;
+        let color = self.side_to_move;
+        let is_pawn_of = |sq: &Square, color: Color| {
+            let p = self.get_piece(sq);
+            piece_type(p) == BITS_PAWN && piece_color(p) == color
+        };
 
-        let step_offsets = vec![
-            (-2, -1),
-            (-2, 1),
-            (-1, -2),
-            (-1, 2),
-            (1, -2),
-            (1, 2),
-            (2, 1),
-            (2, 2),
-        ];
-        for (file_step, rank_step) in step_offsets {
-            let dest_file = file as i32 + file_step;
-            let dest_rank = rank as i32 + rank_step;
-            if dest_file >= 0 && dest_file < 8 && dest_rank >= 0 && dest_rank < 8 {
-                let p = self.pieces[dest_file as usize][dest_rank as usize];
-                if !(is_piece(p) && piece_color(p) == knight_color) {
-                    res.push(Square(dest_file as usize, dest_rank as usize));
-                }
+        // Pawns that could capture on the square are where a pawn of the other side would attack
+        let capturers = bitboard::PAWN_ATTACKS[color_index(opposite_color(color))][ep.index()]
+            & self.occupancy[color_index(color)];
+        bitboard::squares(capturers)
+            .filter(|from| is_pawn_of(from, color))
+            .filter(|from| is_pawn_of(&Square(ep.0, from.1), opposite_color(color)))
+            .any(|from| !self.leaves_king_in_check(&Move::new(from, ep)))
+            .then_some(ep)
+    }
+
+    /// Pack the position into a compact byte encoding in `buf` and return its length in bytes. For
+    /// positions with at most 32 pieces the encoding is at most 192 bits long.
+    ///
+    /// The first 8 bytes are the occupancy bitboard (little endian, bit 0 = A1, bit 63 = H8),
+    /// followed by a 4-bit code per occupied square, in square order, two codes per byte with the
+    /// lower nibble first:
+    ///
+    ///    0..=5   White pawn, knight, bishop, rook, queen, king
+    ///    6..=11  Black pawn, knight, bishop, rook, queen, king
+    ///    12      Pawn that can be captured en passant
+    ///    13      White rook that can still castle
+    ///    14      Black rook that can still castle
+    ///    15      Black king, when Black is to move
+    fn pack(&self, buf: &mut [u8; PACKED_MAX_LEN]) -> usize {
+        let rights = self.castling_rights();
+        let en_passant_pawn = self.legal_en_passant().map(|sq| {
+            if sq.1 == 2 {
+                Square(sq.0, 3)
+            } else {
+                Square(sq.0, 4)
+            }
+        });
+
+        let mut occupancy: Bitboard = bitboard::EMPTY;
+        let mut codes = [0; 64];
+        let mut num_codes = 0;
+        for (rank, file) in (0..8).cartesian_product(0..8) {
+            let sq = Square(file, rank);
+            let p = self.get_piece(&sq);
+            if !is_piece(p) {
+                continue;
             }
+            occupancy |= bitboard::from_square(&sq);
+
+            let castling_rook = match (file, rank) {
+                (7, 0) => rights & CASTLE_WHITE_KINGSIDE,
+                (0, 0) => rights & CASTLE_WHITE_QUEENSIDE,
+                (7, 7) => rights & CASTLE_BLACK_KINGSIDE,
+                (0, 7) => rights & CASTLE_BLACK_QUEENSIDE,
+                _ => CASTLE_NONE,
+            } != CASTLE_NONE
+                && piece_type(p) == BITS_ROOK;
+
+            let code: u8 = if Some(sq) == en_passant_pawn && piece_type(p) == BITS_PAWN {
+                12
+            } else if castling_rook {
+                if piece_color(p) == BITS_WHITE {
+                    13
+                } else {
+                    14
+                }
+            } else if piece_type(p) == BITS_KING
+                && piece_color(p) == BITS_BLACK
+                && self.side_to_move() == BITS_BLACK
+            {
+                15
+            } else {
+                let type_code = match piece_type(p) {
+                    BITS_PAWN => 0,
+                    BITS_KNIGHT => 1,
+                    BITS_BISHOP => 2,
+                    BITS_ROOK => 3,
+                    BITS_QUEEN => 4,
+                    _ => 5,
+                };
+                if piece_color(p) == BITS_WHITE {
+                    type_code
+                } else {
+                    type_code + 6
+                }
+            };
+            codes[num_codes] = code;
+            num_codes += 1;
+        }
+
+        buf[..8].copy_from_slice(&occupancy.to_le_bytes());
+        let packed_codes = codes[..num_codes]
+            .chunks(2)
+            .map(|pair| pair[0] | (pair.get(1).unwrap_or(&0) << 4));
+        for (byte, packed) in buf[8..].iter_mut().zip(packed_codes) {
+            *byte = packed;
         }
 
-        res.iter().map(|dest| (from, *dest)).collect_vec()
+        8 + num_codes.div_ceil(2)
     }
 
-    fn gen_queen_moves(&self, &from: &Square) -> Vec<Move> {
-        self.gen_bishop_moves(&from)
-            .into_iter()
-            .chain(self.gen_rook_moves(&from).into_iter())
-            .collect_vec()
+    pub fn gen_moves(&self) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.gen_moves_into(&mut moves, Promotions::All);
+
+        moves.to_vec()
     }
 
-    // TODO: Refactor this. It shouldn't require a mut reference.
-    fn move_cause_self_check(&mut self, move_: Move) -> bool {
-        let from = move_.0;
-        let to = move_.1;
+    /// Replace the contents of `moves` with the legal moves of the side to move, in the same order
+    /// as `gen_moves`. Unlike `gen_moves`, this doesn't allocate. With `Promotions::QueenOnly`,
+    /// underpromotions are not generated at all.
+    pub fn gen_moves_into(&self, moves: &mut MoveList, promotions: Promotions) {
+        self.gen_pseudo_legal_moves_into(moves, promotions);
+        moves.retain(|mv| !self.leaves_king_in_check(mv));
+    }
 
-        assert!(piece_color(self.pieces[from.0][from.1]) == self.side_to_move());
+    /// Iterate over the legal moves of the side to move, in the same order as `gen_moves`. The
+    /// legality of each move is checked as the iterator advances, so stopping early skips the
+    /// work for the remaining moves.
+    pub fn legal_moves(&self) -> impl Iterator<Item = Move> + '_ {
+        let mut moves = MoveList::new();
+        self.gen_pseudo_legal_moves_into(&mut moves, Promotions::All);
 
-        // Do the move temporarily
-        let target_sq_state = self.pieces[to.0][to.1];
-        self.pieces[to.0][to.1] = self.pieces[from.0][from.1];
-        self.pieces[from.0][from.1] = BITS_NO_PIECE;
+        moves
+            .into_iter()
+            .filter(|mv| !self.leaves_king_in_check(mv))
+    }
 
-        // Check for self check
-        let in_check = self.check_for_check(self.side_to_move());
+    /// Iterate over the legal moves of the side to move in two stages: first the captures and
+    /// promotions, then the quiet moves. Within each stage, the moves come in the same order as in
+    /// `gen_moves`.
+    ///
+    /// The quiet moves are only checked for legality once the first stage is exhausted, so a
+    /// search that cuts off on a capture doesn't pay for them.
+    pub fn staged_moves(&self) -> impl Iterator<Item = Move> + '_ {
+        let is_noisy = |mv: &Move| mv.is_capture() || mv.is_promotion();
+        let mut moves = MoveList::new();
+        self.gen_pseudo_legal_moves_into(&mut moves, Promotions::All);
+
+        let quiet_moves = moves.clone().into_iter().filter(move |mv| !is_noisy(mv));
+        moves
+            .into_iter()
+            .filter(is_noisy)
+            .chain(quiet_moves)
+            .filter(|mv| !self.leaves_king_in_check(mv))
+    }
 
-        // Revert the move
-        self.pieces[from.0][from.1] = self.pieces[to.0][to.1];
-        self.pieces[to.0][to.1] = target_sq_state;
+    /// Generate the pseudo-legal moves of the side to move, i.e. the moves that follow the
+    /// movement rules of the pieces but may leave the own king in check. Castling moves are only
+    /// generated if the king doesn't castle out of or through check.
+    ///
+    /// Engines that check legality lazily can combine this with `leaves_king_in_check`.
+    pub fn gen_pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = MoveList::new();
+        self.gen_pseudo_legal_moves_into(&mut moves, Promotions::All);
 
-        in_check
+        moves.to_vec()
     }
 
-    fn check_for_check(&self, color: Color) -> bool {
-        // TODO: Optimize this code
-        // Find the king
-        let mut king_file: usize = 0x0badf00d;
-        let mut king_rank: usize = 0xdeadbeef;
-        for file in 0..8 {
-            for rank in 0..8 {
-                let p = self.pieces[file][rank];
-                if piece_type(p) == BITS_KING && piece_color(p) == color {
-                    king_file = file;
-                    king_rank = rank;
-                }
-            }
+    /// Replace the contents of `moves` with the pseudo-legal moves of the side to move (see
+    /// `gen_pseudo_legal_moves`) without allocating. `promotions` selects which promotions are
+    /// generated.
+    pub fn gen_pseudo_legal_moves_into(&self, moves: &mut MoveList, promotions: Promotions) {
+        moves.clear();
+        for from in self.own_piece_squares() {
+            self.gen_piece_moves(&from, moves, promotions);
         }
-        let kf = king_file as i32;
-        let kr = king_rank as i32;
-        let king_sq = Square(king_file, king_rank);
+    }
+
+    /// Check if playing the pseudo-legal move `mv` would leave the king of the side to move in
+    /// check.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the square the move starts from does not hold a piece of the side to move.
+    pub fn leaves_king_in_check(&self, mv: &Move) -> bool {
+        let from = mv.from;
+
+        assert!(piece_color(self.pieces[from.0][from.1]) == self.side_to_move());
+
+        // Do the move on a copy of the position
+        let mut after = self.without_history();
+        after.execute_move(*mv);
 
-        // Flip pawn facing direction since the opponents pawns are interesting
-        let pawn_facing_dir: i32 = if color == BITS_WHITE { -1 } else { 1 };
+        after.in_check(self.side_to_move())
+    }
 
-        // Does a pawn threaten the king from the right file?
-        let p = self.get_piece_unbounded(kf + 1, kr - pawn_facing_dir);
-        if piece_color(p) != color && piece_type(p) == BITS_PAWN {
-            return true;
+    /// Check if `mv` is a legal move in this position. This validates the single move directly,
+    /// which is cheaper than looking for it among the generated moves.
+    pub fn is_legal(&self, mv: &Move) -> bool {
+        let Move { from, to, .. } = *mv;
+        let piece = self.get_piece(&from);
+        if !is_piece(piece) || piece_color(piece) != self.side_to_move() {
+            return false;
+        }
+        let target = self.get_piece(&to);
+        if from == to || (is_piece(target) && piece_color(target) == piece_color(piece)) {
+            return false;
         }
 
-        // Does a pawn threaten the king from the left file?
-        let p = self.get_piece_unbounded(kf - 1, kr - pawn_facing_dir);
-        if piece_color(p) != color && piece_type(p) == BITS_PAWN {
-            return true;
+        let is_promotion = piece_type(piece) == BITS_PAWN && (to.1 == 0 || to.1 == 7);
+        let promotion_ok = match mv.promotion {
+            Some(p_type) => is_promotion && PROMOTION_PIECES.contains(&p_type),
+            None => !is_promotion,
+        };
+        if !promotion_ok {
+            return false;
         }
 
-        // Does the other king threaten the king? This can never happen in a real game,
-        // but this needs to be checked to validate if the board is valid after a move.
-        for file in (kf - 1)..(kf + 1) {
-            for rank in (kr - 1)..(kr + 1) {
-                let p = self.get_piece_unbounded(file, rank);
-                if piece_type(p) == BITS_KING && piece_color(p) != color {
-                    return true;
+        let file_step = to.0 as i32 - from.0 as i32;
+        let rank_step = to.1 as i32 - from.1 as i32;
+        let pattern_ok = match piece_type(piece) {
+            BITS_KNIGHT => {
+                let steps = (file_step.abs(), rank_step.abs());
+                steps == (1, 2) || steps == (2, 1)
+            }
+            BITS_KING if file_step.abs() == 2 && rank_step == 0 => {
+                let mut castling_moves = MoveList::new();
+                self.gen_castling_moves(&from, &mut castling_moves);
+                castling_moves.contains(mv)
+            }
+            BITS_KING => file_step.abs() <= 1 && rank_step.abs() <= 1,
+            BITS_BISHOP => file_step.abs() == rank_step.abs() && self.path_is_empty(&from, &to),
+            BITS_ROOK => (file_step == 0 || rank_step == 0) && self.path_is_empty(&from, &to),
+            BITS_QUEEN => {
+                (file_step == 0 || rank_step == 0 || file_step.abs() == rank_step.abs())
+                    && self.path_is_empty(&from, &to)
+            }
+            BITS_PAWN => {
+                let (facing_dir, start_rank) = if piece_color(piece) == BITS_WHITE {
+                    (1, 1)
+                } else {
+                    (-1, 6)
+                };
+                match (file_step.abs(), rank_step * facing_dir) {
+                    (0, 1) => !is_piece(target),
+                    (0, 2) => {
+                        from.1 == start_rank && !is_piece(target) && self.path_is_empty(&from, &to)
+                    }
+                    (1, 1) => is_piece(target) || self.en_passant == Some(to),
+                    _ => false,
                 }
             }
-        }
+            _ => false,
+        };
 
-        // Check for knight
-        let knight_offsets = vec![
-            (1, 2),
-            (-1, 2),
-            (1, -2),
-            (-1, -2),
-            (2, 1),
-            (2, -1),
-            (-2, 1),
-            (-2, -1),
-        ];
-        for offset in &knight_offsets {
-            let file = kf + offset.0;
-            let rank = kr + offset.1;
-            let p = self.get_piece_unbounded(file, rank);
-            if piece_type(p) == BITS_KNIGHT && piece_color(p) != color {
-                return true;
-            }
-        }
+        pattern_ok && !self.leaves_king_in_check(mv)
+    }
 
-        // Check for bishop or queen (diagonally)
-        let bishop_dirs = vec![(1, 1), (-1, 1), (-1, -1), (1, -1)];
-        for dir in &bishop_dirs {
-            let (p, _) = self.walk_to_piece_or_border(&king_sq, dir.0, dir.1);
-            if (piece_type(p) == BITS_BISHOP || piece_type(p) == BITS_QUEEN)
-                && piece_color(p) != color
-            {
-                return true;
-            }
+    /// Generate the legal moves of the piece on `sq`. No moves are generated if the square is
+    /// empty or holds a piece of the side not to move.
+    pub fn gen_moves_from(&self, sq: &Square) -> Vec<Move> {
+        let piece = self.get_piece(sq);
+        if !is_piece(piece) || piece_color(piece) != self.side_to_move() {
+            return Vec::new();
         }
 
-        // Check for rook or queen (orthogonally)
-        let rook_dirs = vec![(-1, 0), (1, 0), (0, -1), (0, 1)];
-        for dir in &rook_dirs {
-            let (p, _) = self.walk_to_piece_or_border(&king_sq, dir.0, dir.1);
-            if (piece_type(p) == BITS_ROOK || piece_type(p) == BITS_QUEEN)
-                && piece_color(p) != color
-            {
-                return true;
-            }
-        }
+        let mut moves = MoveList::new();
+        self.gen_piece_moves(sq, &mut moves, Promotions::All);
+        moves.retain(|mv| !self.leaves_king_in_check(mv));
 
-        false
+        moves.to_vec()
     }
 
-    /// Walk in a specified direction from a starting square until a piece or border is found.
-    ///
-    /// This function starts from a given square and moves stepwise as defined by
-    /// `file_step_sz` and `rank_step_sz`. It continues to move in this direction, step
-    /// by step, until it either finds a piece or reaches the edge of the board. If a
-    /// piece is found, the function returns the piece and the number of steps taken to
-    /// reach it. If no piece is found and the edge of the board is reached, it returns
-    /// `BITS_NO_PIECE` and -1.
-    ///
-    /// # Returns
-    ///
-    /// A tuple where the first element is the `Piece` found (or `BITS_NO_PIECE` if no
-    /// piece is found) and the second element is the number of steps taken to find the
-    /// piece, or the number of steps to the border in case no piece was found.
-    fn walk_to_piece_or_border(
-        &self,
-        start: &Square,
-        file_step_sz: i32,
-        rank_step_sz: i32,
-    ) -> (Piece, usize) {
-        let mut sq = Square(
-            (start.0 as i32 + file_step_sz) as usize,
-            (start.1 as i32 + rank_step_sz) as usize,
-        );
-        let mut steps_taken = 0;
+    /// Count the legal moves for the side to move without collecting them into a list.
+    pub fn count_moves(&self) -> usize {
+        self.legal_moves().count()
+    }
 
-        while (0..8).contains(&sq.0) && (0..8).contains(&sq.1) {
-            steps_taken += 1;
+    /// Check if the side to move has at least one legal move. Returns as soon as one is found.
+    pub fn has_any_legal_move(&self) -> bool {
+        self.legal_moves().next().is_some()
+    }
 
-            let p = self.pieces[sq.0][sq.1];
-            if p != BITS_NO_PIECE {
-                return (p, steps_taken);
-            }
+    /// Check if the side to move is checkmated, i.e. in check without any legal move.
+    pub fn is_checkmate(&self) -> bool {
+        self.in_check_to_move() && !self.has_any_legal_move()
+    }
 
-            sq.0 = (sq.0 as i32 + file_step_sz) as usize;
-            sq.1 = (sq.1 as i32 + rank_step_sz) as usize;
-        }
+    /// Check if the side to move is stalemated, i.e. has no legal move without being in check.
+    pub fn is_stalemate(&self) -> bool {
+        !self.in_check_to_move() && !self.has_any_legal_move()
+    }
 
-        (BITS_NO_PIECE, steps_taken)
+    /// Move the piece on `from` to `to` and pass the turn to the other side. Moves to the last rank
+    /// with a pawn must specify the piece type to promote to; for all other moves `promotion` must
+    /// be None.
+    ///
+    /// The move is recorded in the history of the board, so it can be taken back with `undo_move`
+    /// and is taken into account by `is_threefold_repetition`.
+    ///
+    /// Returns an error and leaves the board untouched if the move is not legal.
+    pub fn move_piece(
+        &mut self,
+        from: &Square,
+        to: &Square,
+        promotion: Option<Piece>,
+    ) -> Result<()> {
+        let hash = self.zobrist_hash();
+        let undo = self.make_move(&Move {
+            promotion,
+            ..Move::new(*from, *to)
+        })?;
+
+        self.position_hashes.push(hash);
+        self.history.push(undo.entry);
+
+        Ok(())
     }
 
-    fn straight_path(&self, start: &Square, file_step_sz: i32, rank_step_sz: i32) -> Vec<Square> {
-        let piece = self.get_piece(start);
-        assert!(is_piece(piece));
-        let p_color = piece_color(piece);
+    /// Take back the last move applied with `move_piece`. Returns the move that was taken back, or
+    /// None if there is no move to take back.
+    pub fn undo_move(&mut self) -> Option<Move> {
+        let entry = self.history.pop()?;
+        self.position_hashes.pop();
 
-        let (p, steps) = self.walk_to_piece_or_border(&start, file_step_sz, rank_step_sz);
-        let mut moves = (1..steps)
-            .map(|x| {
-                Square(
-                    (start.0 as i32 + (file_step_sz * x as i32)) as usize,
-                    (start.1 as i32 + rank_step_sz * x as i32) as usize,
-                )
-            })
-            .collect_vec();
+        let move_ = entry.move_;
+        self.take_back(&entry);
 
-        if !is_piece(p) || is_piece(p) && piece_color(p) != p_color {
-            moves.push(Square(
-                (start.0 as i32 + file_step_sz * steps as i32) as usize,
-                (start.1 as i32 + rank_step_sz * steps as i32) as usize,
-            ));
+        Some(move_)
+    }
+
+    /// Play `mv` and pass the turn to the other side, without recording the move in the history
+    /// of the board. The returned token takes the move back when passed to `unmake_move`.
+    ///
+    /// This is cheaper than `move_piece` and meant for searches, which take back every move they
+    /// make in reverse order.
+    ///
+    /// Returns an error and leaves the board untouched if the move is not legal.
+    pub fn make_move(&mut self, mv: &Move) -> Result<Undo> {
+        if !self.is_legal(mv) {
+            return Err(chess_error("Not a valid move"));
         }
 
-        moves
+        Ok(self.make_legal_move(mv))
     }
 
-    fn get_piece_unbounded(&self, file: i32, rank: i32) -> Piece {
-        if file >= 0 && file < 8 && rank >= 0 && rank < 8 {
-            self.pieces[file as usize][rank as usize]
+    /// Like `make_move`, but without checking that the move is legal, for moves that come from
+    /// the move generator.
+    pub(crate) fn make_legal_move(&mut self, mv: &Move) -> Undo {
+        let move_ = self.annotate(*mv);
+        let castling_rights = self.castling_rights();
+
+        let entry = self.execute_move(move_);
+        if piece_type(entry.moved) == BITS_PAWN || is_piece(entry.captured) {
+            self.halfmove_clock = 0;
         } else {
-            0
+            self.halfmove_clock += 1;
+        }
+        self.en_passant = if move_.is_double_step() {
+            Some(Square(move_.from.0, (move_.from.1 + move_.to.1) / 2))
+        } else {
+            None
+        };
+        self.toggle_side_to_move();
+
+        Undo {
+            entry,
+            castling_rights,
         }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn pawns() -> crate::Result<()> {
-        crate::internal::test_utils::json::run_check_num_moves_test("test_cases/pawns.json")
+    /// Take back the move that `undo` was returned for by `make_move`. Moves must be taken back in
+    /// the reverse order they were made in.
+    pub fn unmake_move(&mut self, undo: Undo) {
+        self.take_back(&undo.entry);
     }
 
-    #[test]
+    /// Pass the turn to the other side without moving a piece, as done by null-move pruning in
+    /// searches. The en passant square is cleared, since the chance to capture en passant is lost.
+    /// Nothing else changes, including the halfmove clock.
+    ///
+    /// The side to move must not be in check, or the other side could capture the king.
+    pub fn make_null_move(&mut self) -> NullMoveUndo {
+        let undo = NullMoveUndo {
+            en_passant: self.en_passant.take(),
+        };
+        self.toggle_side_to_move();
+
+        undo
+    }
+
+    /// Take back a null move made with `make_null_move`. As for `unmake_move`, moves must be taken
+    /// back in the reverse order they were made in.
+    pub fn undo_null_move(&mut self, undo: NullMoveUndo) {
+        self.en_passant = undo.en_passant;
+        self.toggle_side_to_move();
+    }
+
+    /// Check if the fifty-move rule applies, i.e. if no capture or pawn move has been made in the
+    /// last fifty moves by each side. A checkmate delivered with the last of those moves takes
+    /// precedence.
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.halfmove_clock >= 100 && !self.is_checkmate()
+    }
+
+    /// Check if the current position has occurred at least three times, counting the positions
+    /// reached with `move_piece` since the board was created. Positions are considered equal when
+    /// the pieces, the side to move, the castling rights and the en passant square are the same.
+    pub fn is_threefold_repetition(&self) -> bool {
+        let hash = self.zobrist_hash();
+        let occurrences = self.position_hashes.iter().filter(|h| **h == hash).count() + 1;
+
+        occurrences >= 3
+    }
+
+    /// Check if neither side can possibly checkmate, i.e. if the only pieces left besides the
+    /// kings are a single knight or bishop, or bishops that all stand on squares of the same
+    /// color.
+    pub fn is_insufficient_material(&self) -> bool {
+        let mut minor_pieces = Vec::new();
+        for (sq, p) in self.pieces_iter() {
+            match piece_type(p) {
+                BITS_KING => {}
+                BITS_KNIGHT | BITS_BISHOP => minor_pieces.push((sq, piece_type(p))),
+                _ => return false,
+            }
+        }
+
+        match minor_pieces.as_slice() {
+            [] | [_] => true,
+            [(first, _), ..] => minor_pieces
+                .iter()
+                .all(|(sq, p_type)| *p_type == BITS_BISHOP && sq.is_dark() == first.is_dark()),
+        }
+    }
+
+    /// Whether the game is over, checking the ways it can end in order: checkmate, stalemate,
+    /// insufficient material, the fifty-move rule and threefold repetition. The draws by the
+    /// fifty-move rule and repetition are the ones a player can claim; they are reported as soon
+    /// as they are available.
+    pub fn status(&self) -> GameStatus {
+        if !self.has_any_legal_move() {
+            if self.in_check_to_move() {
+                GameStatus::Checkmate(opposite_color(self.side_to_move))
+            } else {
+                GameStatus::Stalemate
+            }
+        } else if self.is_insufficient_material() {
+            GameStatus::DrawByInsufficientMaterial
+        } else if self.halfmove_clock >= 100 {
+            GameStatus::DrawByFiftyMove
+        } else if self.is_threefold_repetition() {
+            GameStatus::DrawByThreefoldRepetition
+        } else {
+            GameStatus::Ongoing
+        }
+    }
+
+    /// The draw the side to move can claim by writing down `mv` and declaring the intention to
+    /// play it, as the FIDE Laws allow: the claim is valid if the position after `mv` is a
+    /// threefold repetition or the fifty-move rule applies to it. The move is not played.
+    ///
+    /// Returns None if no draw can be claimed this way and an error if the move is not legal.
+    pub fn draw_claim_with(&self, mv: &Move) -> Result<Option<GameStatus>> {
+        // Repetitions are found in the history, so the copy needs it
+        let mut after = self.clone();
+        after.move_piece(&mv.from, &mv.to, mv.promotion)?;
+
+        if after.is_fifty_move_draw() {
+            Ok(Some(GameStatus::DrawByFiftyMove))
+        } else if after.is_threefold_repetition() {
+            Ok(Some(GameStatus::DrawByThreefoldRepetition))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn toggle_side_to_move(&mut self) {
+        self.side_to_move = opposite_color(self.side_to_move);
+    }
+
+    /// Squares occupied by the pieces of the side to move.
+    fn own_piece_squares(&self) -> impl Iterator<Item = Square> {
+        bitboard::squares(self.occupancy[color_index(self.side_to_move)])
+    }
+
+    /// Add the pseudo-legal moves of the piece on `from` to `moves`, i.e. without checking for self
+    /// check.
+    fn gen_piece_moves(&self, from: &Square, moves: &mut MoveList, promotions: Promotions) {
+        let start = moves.len();
+        match piece_type(self.get_piece(from)) {
+            BITS_KING => self.gen_king_moves(from, moves),
+            BITS_PAWN => self.gen_pawn_moves(from, moves, promotions),
+            BITS_ROOK => self.gen_rook_moves(from, moves),
+            BITS_KNIGHT => self.gen_knight_moves(from, moves),
+            BITS_BISHOP => self.gen_bishop_moves(from, moves),
+            BITS_QUEEN => self.gen_queen_moves(from, moves),
+            p => panic!("Piece type {p} Not implemented yet"),
+        }
+
+        for mv in &mut moves[start..] {
+            *mv = self.annotate(*mv);
+        }
+    }
+
+    /// Fill in the moved piece, the captured piece and the flags of a move in this position.
+    fn annotate(&self, move_: Move) -> Move {
+        let Move { from, to, .. } = move_;
+        let moved = self.get_piece(&from);
+        let mut captured = self.get_piece(&to);
+        let mut flags = MOVE_FLAGS_NONE;
+
+        match piece_type(moved) {
+            BITS_KING if from.0.abs_diff(to.0) == 2 => flags |= MOVE_CASTLING,
+            BITS_PAWN if from.1.abs_diff(to.1) == 2 => flags |= MOVE_DOUBLE_STEP,
+            BITS_PAWN if from.0 != to.0 && !is_piece(captured) => {
+                flags |= MOVE_EN_PASSANT;
+                captured = self.get_piece(&Square(to.0, from.1));
+            }
+            _ => {}
+        }
+
+        Move {
+            moved,
+            captured,
+            flags,
+            ..move_
+        }
+    }
+
+    fn gen_king_moves(&self, from: &Square, moves: &mut MoveList) {
+        assert_eq!(piece_type(self.pieces[from.0][from.1]), BITS_KING);
+
+        self.gen_moves_to(from, bitboard::KING_ATTACKS[from.index()], moves);
+        self.gen_castling_moves(from, moves);
+    }
+
+    fn gen_castling_moves(&self, from: &Square, moves: &mut MoveList) {
+        let color = piece_color(self.get_piece(from));
+        let (rank, kingside, queenside) = if color == BITS_WHITE {
+            (0, CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE)
+        } else {
+            (7, CASTLE_BLACK_KINGSIDE, CASTLE_BLACK_QUEENSIDE)
+        };
+        let rights = self.castling_rights();
+
+        if *from != Square(4, rank) || rights & (kingside | queenside) == CASTLE_NONE {
+            return;
+        }
+        let attacks = self.attacked_squares(opposite_color(color));
+        // Castling out of check is not allowed
+        if bitboard::contains(attacks, from) {
+            return;
+        }
+
+        // The squares between king and rook must be empty and the king may not pass through an
+        // attacked square. Whether the destination square is attacked is checked along with all
+        // other moves in gen_moves.
+        let path_is_free = |between: &[usize], passed: usize| {
+            between.iter().all(|f| !is_piece(self.pieces[*f][rank]))
+                && !bitboard::contains(attacks, &Square(passed, rank))
+        };
+
+        if rights & kingside != CASTLE_NONE && path_is_free(&[5, 6], 5) {
+            moves.push(Move::new(*from, Square(6, rank)));
+        }
+        if rights & queenside != CASTLE_NONE && path_is_free(&[1, 2, 3], 3) {
+            moves.push(Move::new(*from, Square(2, rank)));
+        }
+    }
+
+    fn gen_pawn_moves(&self, from: &Square, moves: &mut MoveList, promotions: Promotions) {
+        let file = from.0;
+        let rank = from.1;
+        let piece = self.pieces[file][rank];
+        let facing_dir: i32 = if self.side_to_move() == BITS_WHITE {
+            1
+        } else {
+            -1
+        };
+
+        assert_eq!(piece_type(self.pieces[from.0][from.1]), BITS_PAWN);
+        assert_eq!(
+            piece_color(self.pieces[from.0][from.1]),
+            self.side_to_move()
+        );
+        assert!(rank > 0);
+        assert!(rank < 7);
+
+        // At most four destinations: two steps forward and two captures
+        let mut dests = [Square(0, 0); 4];
+        let mut num_dests = 0;
+        let mut add_dest = |dest: Square| {
+            dests[num_dests] = dest;
+            num_dests += 1;
+        };
+
+        // Move forward one step
+        let rank_dest = (rank as i32 + facing_dir) as usize;
+        if self.pieces[file][rank_dest] == BITS_NO_PIECE {
+            add_dest(Square(file, rank_dest));
+
+            // Move forward two steps
+            let rank_dest = (rank as i32 + 2 * facing_dir) as usize;
+            if ((rank == 1 && piece_color(piece) == BITS_WHITE)
+                || (rank == 6 && piece_color(piece) == BITS_BLACK))
+                && self.pieces[file][rank_dest] == BITS_NO_PIECE
+            {
+                add_dest(Square(file, rank_dest));
+            }
+        }
+
+        // Capture right
+        if file < 7 {
+            let dest = self.pieces[file + 1][rank_dest];
+            if (is_piece(dest) && piece_color(piece) != piece_color(dest))
+                || self.en_passant == Some(Square(file + 1, rank_dest))
+            {
+                add_dest(Square(file + 1, rank_dest));
+            }
+        }
+
+        // Capture left
+        if file > 0 {
+            let dest = self.pieces[file - 1][rank_dest];
+            if (is_piece(dest) && piece_color(piece) != piece_color(dest))
+                || self.en_passant == Some(Square(file - 1, rank_dest))
+            {
+                add_dest(Square(file - 1, rank_dest));
+            }
+        }
+
+        for dest in &dests[..num_dests] {
+            if rank_dest == 0 || rank_dest == 7 {
+                // Reaching the last rank; one move per promotion piece
+                for p_type in promotions.pieces() {
+                    moves.push(Move::with_promotion(*from, *dest, *p_type));
+                }
+            } else {
+                moves.push(Move::new(*from, *dest));
+            }
+        }
+    }
+
+    fn gen_bishop_moves(&self, from: &Square, moves: &mut MoveList) {
+        self.gen_moves_to(from, magic::bishop_attacks(from, self.occupied()), moves);
+    }
+
+    fn gen_rook_moves(&self, from: &Square, moves: &mut MoveList) {
+        self.gen_moves_to(from, magic::rook_attacks(from, self.occupied()), moves);
+    }
+
+    fn gen_knight_moves(&self, from: &Square, moves: &mut MoveList) {
+        assert_eq!(piece_type(self.pieces[from.0][from.1]), BITS_KNIGHT);
+
+        self.gen_moves_to(from, bitboard::KNIGHT_ATTACKS[from.index()], moves);
+    }
+
+    fn gen_queen_moves(&self, from: &Square, moves: &mut MoveList) {
+        self.gen_moves_to(from, magic::queen_attacks(from, self.occupied()), moves);
+    }
+
+    /// Add the moves of the piece on `from` to the squares in `targets` that are not occupied by
+    /// its own pieces.
+    fn gen_moves_to(&self, from: &Square, targets: Bitboard, moves: &mut MoveList) {
+        assert_eq!(
+            piece_color(self.pieces[from.0][from.1]),
+            self.side_to_move()
+        );
+
+        let own_pieces = self.occupancy[color_index(self.side_to_move)];
+        for to in bitboard::squares(targets & !own_pieces) {
+            moves.push(Move::new(*from, to));
+        }
+    }
+
+    /// Squares occupied by pieces of either color.
+    fn occupied(&self) -> Bitboard {
+        self.occupancy[0] | self.occupancy[1]
+    }
+
+    /// Draw the board as seen by `perspective`, with a label for each rank and file.
+    fn diagram(&self, perspective: Color, symbol: fn(Piece) -> char, empty: char) -> String {
+        let (ranks, files): (Vec<usize>, Vec<usize>) = if perspective == BITS_WHITE {
+            ((0..8).rev().collect(), (0..8).collect())
+        } else {
+            ((0..8).collect(), (0..8).rev().collect())
+        };
+
+        let mut res = String::new();
+        for rank in &ranks {
+            res.push_str(&(rank + 1).to_string());
+            for file in &files {
+                let piece = self.get_piece(&Square(*file, *rank));
+                res.push(' ');
+                res.push(if is_piece(piece) {
+                    symbol(piece)
+                } else {
+                    empty
+                });
+            }
+            res.push('\n');
+        }
+        res.push(' ');
+        for file in &files {
+            res.push(' ');
+            res.push((b'a' + *file as u8) as char);
+        }
+        res.push('\n');
+
+        res
+    }
+
+    /// Copy of the position, without the move history. Unlike `clone`, this never allocates, so
+    /// it suits copy-make workflows. The copy can't take back earlier moves with `undo_move`, and
+    /// only positions reached after the copy count towards repetitions.
+    pub fn without_history(&self) -> Board {
+        Board {
+            pieces: self.pieces,
+            side_to_move: self.side_to_move,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            history: Vec::new(),
+            position_hashes: Vec::new(),
+            king_squares: self.king_squares,
+            occupancy: self.occupancy,
+            piece_hash: self.piece_hash,
+            piece_attacks: self.piece_attacks,
+        }
+    }
+
+    /// Put `piece` on `sq`, keeping the tracked king squares, occupancy, piece hash and piece
+    /// attacks up to date.
+    fn put(&mut self, sq: Square, piece: Piece) {
+        let old = self.get_piece(&sq);
+        if old == piece {
+            return;
+        }
+        if is_piece(old) {
+            self.piece_hash ^= zobrist::piece_key(old, &sq);
+            let color = color_index(piece_color(old));
+            self.occupancy[color] &= !bitboard::from_square(&sq);
+            if self.king_squares[color] == Some(sq) {
+                self.king_squares[color] = None;
+            }
+        }
+
+        if is_piece(piece) {
+            self.piece_hash ^= zobrist::piece_key(piece, &sq);
+            let color = color_index(piece_color(piece));
+            self.occupancy[color] |= bitboard::from_square(&sq);
+            if piece_type(piece) == BITS_KING {
+                self.king_squares[color] = Some(sq);
+            }
+        }
+        self.pieces[sq.0][sq.1] = piece;
+        self.piece_attacks[sq.index()] = self.attacks_of(piece, &sq);
+
+        // Sliders whose lines reach the square are now blocked there or can see past it
+        if is_piece(old) != is_piece(piece) {
+            let changed = bitboard::from_square(&sq);
+            for from in bitboard::squares(self.occupied() & !changed) {
+                let p = self.get_piece(&from);
+                if self.piece_attacks[from.index()] & changed != bitboard::EMPTY
+                    && matches!(piece_type(p), BITS_BISHOP | BITS_ROOK | BITS_QUEEN)
+                {
+                    self.piece_attacks[from.index()] = self.attacks_of(p, &from);
+                }
+            }
+        }
+    }
+
+    /// Recompute the tracked king squares, occupancy, piece hash and piece attacks from scratch,
+    /// after `pieces` has been written to directly.
+    pub(crate) fn track_pieces(&mut self) {
+        self.king_squares = [None; 2];
+        self.occupancy = [bitboard::EMPTY; 2];
+        self.piece_hash = 0;
+        for (file, rank) in (0..8).cartesian_product(0..8) {
+            let piece = self.pieces[file][rank];
+            if is_piece(piece) {
+                self.piece_hash ^= zobrist::piece_key(piece, &Square(file, rank));
+                let color = color_index(piece_color(piece));
+                self.occupancy[color] |= bitboard::from_square(&Square(file, rank));
+                if piece_type(piece) == BITS_KING {
+                    self.king_squares[color] = Some(Square(file, rank));
+                }
+            }
+        }
+        for idx in 0..64 {
+            let sq = Square::from_index(idx);
+            self.piece_attacks[idx] = self.attacks_of(self.get_piece(&sq), &sq);
+        }
+    }
+
+    /// Squares attacked by `piece` standing on `sq`, with the current occupancy.
+    fn attacks_of(&self, piece: Piece, sq: &Square) -> Bitboard {
+        match piece_type(piece) {
+            BITS_PAWN => bitboard::PAWN_ATTACKS[color_index(piece_color(piece))][sq.index()],
+            BITS_KNIGHT => bitboard::KNIGHT_ATTACKS[sq.index()],
+            BITS_KING => bitboard::KING_ATTACKS[sq.index()],
+            BITS_BISHOP => magic::bishop_attacks(sq, self.occupied()),
+            BITS_ROOK => magic::rook_attacks(sq, self.occupied()),
+            BITS_QUEEN => magic::queen_attacks(sq, self.occupied()),
+            _ => bitboard::EMPTY,
+        }
+    }
+
+    /// Move the pieces on the board according to `move_` without checking that the move is legal.
+    ///
+    /// This takes care of the rook hop when castling, of removing the captured pawn when capturing
+    /// en passant and of replacing promoted pawns. Kings and rooks are marked as moved.
+    ///
+    /// Only the pieces are changed. The returned entry can be passed to `revert_move` to restore
+    /// the pieces.
+    fn execute_move(&mut self, move_: Move) -> HistoryEntry {
+        let Move {
+            from,
+            to,
+            promotion,
+            ..
+        } = move_;
+        let moved = self.get_piece(&from);
+        let mut piece = moved;
+        let mut captured_sq = to;
+
+        match piece_type(piece) {
+            BITS_KING if from.0.abs_diff(to.0) == 2 => {
+                // Castling; hop the rook over to the other side of the king
+                let (rook_from, rook_to) = if to.0 > from.0 { (7, 5) } else { (0, 3) };
+                let rook = self.pieces[rook_from][from.1];
+                self.put(Square(rook_to, from.1), rook | BITS_HAS_MOVED);
+                self.put(Square(rook_from, from.1), BITS_NO_PIECE);
+            }
+            BITS_PAWN if from.0 != to.0 && !is_piece(self.get_piece(&to)) => {
+                // En passant; the captured pawn is beside the moving pawn, not on the target square
+                captured_sq = Square(to.0, from.1);
+            }
+            _ => {}
+        }
+
+        let captured = self.get_piece(&captured_sq);
+        self.put(captured_sq, BITS_NO_PIECE);
+
+        if let Some(p_type) = promotion {
+            piece = piece_color(piece) | p_type;
+        }
+        if piece_type(piece) == BITS_KING || piece_type(piece) == BITS_ROOK {
+            piece |= BITS_HAS_MOVED;
+        }
+
+        self.put(to, piece);
+        self.put(from, BITS_NO_PIECE);
+
+        HistoryEntry {
+            move_,
+            moved,
+            captured,
+            captured_sq,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+        }
+    }
+
+    /// Take back the move in `entry`, restoring everything but the history of the board.
+    fn take_back(&mut self, entry: &HistoryEntry) {
+        self.revert_move(entry);
+        self.en_passant = entry.en_passant;
+        self.halfmove_clock = entry.halfmove_clock;
+        self.toggle_side_to_move();
+    }
+
+    /// Restore the pieces to where they were before the move in `entry` was executed.
+    fn revert_move(&mut self, entry: &HistoryEntry) {
+        let Move { from, to, .. } = entry.move_;
+
+        self.put(to, BITS_NO_PIECE);
+        self.put(from, entry.moved);
+        self.put(entry.captured_sq, entry.captured);
+
+        if piece_type(entry.moved) == BITS_KING && from.0.abs_diff(to.0) == 2 {
+            // Castling; hop the rook back. It can't have moved before castling.
+            let (rook_from, rook_to) = if to.0 > from.0 { (7, 5) } else { (0, 3) };
+            let rook = self.pieces[rook_to][from.1];
+            self.put(Square(rook_from, from.1), rook & !BITS_HAS_MOVED);
+            self.put(Square(rook_to, from.1), BITS_NO_PIECE);
+        }
+    }
+
+    /// Check that the position could occur in a game:
+    ///
+    /// * Each side has exactly one king.
+    /// * There are no pawns on the first or last rank.
+    /// * The side that just moved is not in check.
+    /// * The en passant square, if any, is right behind a pawn that could just have made a double
+    ///   step.
+    ///
+    /// Castling rights are derived from the pieces, so they are always consistent with the
+    /// position. `fen::import_validated` also checks that the castling ability field of a FEN
+    /// string agrees with them.
+    ///
+    /// Returns an error describing the first problem found.
+    pub fn validate(&self) -> Result<()> {
+        for (color, name) in [(BITS_WHITE, "White"), (BITS_BLACK, "Black")] {
+            let num_kings = self
+                .pieces_iter()
+                .filter(|(_, p)| piece_type(*p) == BITS_KING && piece_color(*p) == color)
+                .count();
+            if num_kings != 1 {
+                return Err(chess_error(&format!(
+                    "{name} has {num_kings} kings, expected 1"
+                )));
+            }
+        }
+
+        if let Some((sq, _)) = self
+            .pieces_iter()
+            .find(|(sq, p)| piece_type(*p) == BITS_PAWN && (sq.1 == 0 || sq.1 == 7))
+        {
+            return Err(chess_error(&format!(
+                "Pawn on {}",
+                sq.to_str().to_lowercase()
+            )));
+        }
+
+        if self.in_check(opposite_color(self.side_to_move)) {
+            return Err(chess_error("The side that is not to move is in check"));
+        }
+
+        if let Some(ep) = self.en_passant {
+            // Ranks of the en passant square, the pawn that made the double step and the square it
+            // came from
+            let (ep_rank, pawn_rank, from_rank) = if self.side_to_move == BITS_WHITE {
+                (5, 4, 6)
+            } else {
+                (2, 3, 1)
+            };
+            let pawn = self.get_piece(&Square(ep.0, pawn_rank));
+            let plausible = ep.1 == ep_rank
+                && !is_piece(self.get_piece(&ep))
+                && !is_piece(self.get_piece(&Square(ep.0, from_rank)))
+                && piece_type(pawn) == BITS_PAWN
+                && piece_color(pawn) != self.side_to_move;
+            if !plausible {
+                return Err(chess_error(&format!(
+                    "Invalid en passant square {}",
+                    ep.to_str().to_lowercase()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check if the king of `color` is attacked. Positions without a king of that color are never
+    /// in check.
+    pub fn in_check(&self, color: Color) -> bool {
+        let attacks = self.attacked_squares(opposite_color(color));
+        self.king_square(color)
+            .is_some_and(|sq| bitboard::contains(attacks, &sq))
+    }
+
+    /// The square of the king of `color`, or None if there is no such king. If there are several,
+    /// any of them is returned.
+    ///
+    /// The squares of the kings are tracked as the pieces move, so this is usually a single lookup.
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        let is_king = |p: Piece| piece_type(p) == BITS_KING && piece_color(p) == color;
+
+        match self.king_squares[color_index(color)] {
+            // Removing a king forgets its square, even if there is another king of the same color
+            Some(sq) if is_king(self.get_piece(&sq)) => Some(sq),
+            _ => self
+                .pieces_iter()
+                .find(|(_, p)| is_king(*p))
+                .map(|(sq, _)| sq),
+        }
+    }
+
+    /// Mask of all squares attacked by the pieces of `color`, including squares occupied by its
+    /// own pieces. Pins are not considered. Use `bitboard::squares` to iterate over the squares.
+    ///
+    /// The attacks of each piece are kept up to date as the pieces move, so this only combines
+    /// them.
+    pub fn attacked_squares(&self, color: Color) -> Bitboard {
+        bitboard::squares(self.occupancy[color_index(color)]).fold(bitboard::EMPTY, |bb, sq| {
+            bb | self.piece_attacks[sq.index()]
+        })
+    }
+
+    /// Check if the side to move is in check.
+    pub fn in_check_to_move(&self) -> bool {
+        self.in_check(self.side_to_move())
+    }
+
+    /// All pieces of color `by` that attack `sq`, along with their squares. Pins are not
+    /// considered, and pieces standing behind other attackers (x-rays) are not included.
+    pub fn attackers_of(&self, sq: &Square, by: Color) -> Vec<(Square, Piece)> {
+        let file = sq.0 as i32;
+        let rank = sq.1 as i32;
+        let mut attackers = Vec::new();
+        let mut add_if = |file: i32, rank: i32, types: &[Piece]| {
+            let p = self.get_piece_unbounded(file, rank);
+            if piece_color(p) == by && types.contains(&piece_type(p)) {
+                attackers.push((Square(file as usize, rank as usize), p));
+            }
+        };
+
+        // Pawns attack towards the opponent, so look in the opposite direction
+        let pawn_rank = if by == BITS_WHITE { rank - 1 } else { rank + 1 };
+        add_if(file - 1, pawn_rank, &[BITS_PAWN]);
+        add_if(file + 1, pawn_rank, &[BITS_PAWN]);
+
+        let neighbors = [
+            (bitboard::KNIGHT_ATTACKS[sq.index()], BITS_KNIGHT),
+            (bitboard::KING_ATTACKS[sq.index()], BITS_KING),
+        ];
+        for (attacks, p_type) in neighbors {
+            for from in bitboard::squares(attacks) {
+                add_if(from.0 as i32, from.1 as i32, &[p_type]);
+            }
+        }
+
+        let occupied = self.occupied();
+        for (attacks, slider) in [
+            (magic::bishop_attacks(sq, occupied), BITS_BISHOP),
+            (magic::rook_attacks(sq, occupied), BITS_ROOK),
+        ] {
+            for from in bitboard::squares(attacks) {
+                add_if(from.0 as i32, from.1 as i32, &[slider, BITS_QUEEN]);
+            }
+        }
+
+        attackers
+    }
+
+    /// Check if all squares strictly between `from` and `to` are empty. The squares must be on
+    /// the same file, rank or diagonal.
+    fn path_is_empty(&self, from: &Square, to: &Square) -> bool {
+        let attacks = if from.0 == to.0 || from.1 == to.1 {
+            magic::rook_attacks(from, self.occupied())
+        } else {
+            magic::bishop_attacks(from, self.occupied())
+        };
+
+        bitboard::contains(attacks, to)
+    }
+
+    fn get_piece_unbounded(&self, file: i32, rank: i32) -> Piece {
+        if (0..8).contains(&file) && (0..8).contains(&rank) {
+            self.pieces[file as usize][rank as usize]
+        } else {
+            0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::PieceValues;
+    use crate::internal::test_utils::alloc_counter::count_allocations;
+    use crate::square::square;
+
+    #[test]
+    fn pawns() -> crate::Result<()> {
+        crate::internal::test_utils::json::run_check_num_moves_test("test_cases/pawns.json")
+    }
+
+    #[test]
     fn knights() -> crate::Result<()> {
         crate::internal::test_utils::json::run_check_num_moves_test("test_cases/knights.json")
     }
@@ -453,4 +1661,1151 @@ mod tests {
     fn queen() -> crate::Result<()> {
         crate::internal::test_utils::json::run_check_num_moves_test("test_cases/queens.json")
     }
+
+    #[test]
+    fn castling() -> crate::Result<()> {
+        crate::internal::test_utils::json::run_check_num_moves_test("test_cases/castling.json")
+    }
+
+    #[test]
+    fn promotions() -> crate::Result<()> {
+        crate::internal::test_utils::json::run_check_num_moves_test("test_cases/promotions.json")
+    }
+
+    #[test]
+    fn standard() -> crate::Result<()> {
+        crate::internal::test_utils::json::run_check_num_moves_test("test_cases/standard.json")
+    }
+
+    #[test]
+    fn taxing() -> crate::Result<()> {
+        crate::internal::test_utils::json::run_check_num_moves_test("test_cases/taxing.json")
+    }
+
+    #[test]
+    fn famous() -> crate::Result<()> {
+        crate::internal::test_utils::json::run_check_num_moves_test("test_cases/famous.json")
+    }
+
+    #[test]
+    fn pseudo_legal() -> crate::Result<()> {
+        crate::internal::test_utils::json::run_check_num_moves_test("test_cases/pseudo_legal.json")
+    }
+
+    #[test]
+    fn status() -> crate::Result<()> {
+        crate::internal::test_utils::json::run_check_num_moves_test("test_cases/status.json")
+    }
+
+    #[test]
+    fn resulting_positions() -> crate::Result<()> {
+        use crate::fen::{CMP_CASTLING, CMP_EN_PASSANT, CMP_POS, CMP_SIDE_TO_MOVE};
+
+        for suite in [
+            "test_cases/pawns.json",
+            "test_cases/castling.json",
+            "test_cases/promotions.json",
+            "test_cases/standard.json",
+            "test_cases/taxing.json",
+            "test_cases/famous.json",
+        ] {
+            crate::internal::test_utils::json::run_check_resulting_positions_test(
+                suite,
+                CMP_POS | CMP_SIDE_TO_MOVE | CMP_CASTLING | CMP_EN_PASSANT,
+            )?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unmake_move_restores_the_position() -> crate::Result<()> {
+        let mut board = positions::kiwipete();
+        let fen_before = fen::export(&board);
+
+        for mv in board.gen_moves() {
+            let undo = board.make_move(&mv)?;
+            assert_eq!(board.side_to_move(), BITS_BLACK);
+            assert!(board.history.is_empty());
+
+            board.unmake_move(undo);
+            assert_eq!(fen::export(&board), fen_before, "{mv:?}");
+        }
+
+        assert!(board
+            .make_move(&Move::new(*square!("a1"), *square!("a8")))
+            .is_err());
+        assert_eq!(fen::export(&board), fen_before);
+        Ok(())
+    }
+
+    #[test]
+    fn null_move() -> crate::Result<()> {
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 3 1";
+        let mut board = fen::import(fen)?;
+
+        let undo = board.make_null_move();
+        assert_eq!(fen::export(&board), "4k3/8/8/3pP3/8/8/8/4K3 b - - 3 0");
+        assert_eq!(board.count_moves(), 5 + 1);
+
+        board.undo_null_move(undo);
+        assert_eq!(fen::export(&board), fen::export(&fen::import(fen)?));
+        // En passant is possible again
+        assert!(board.is_legal(&Move::new(*square!("e5"), *square!("d6"))));
+        Ok(())
+    }
+
+    #[test]
+    fn king_square() -> crate::Result<()> {
+        let mut board = fen::import("4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1")?;
+        assert_eq!(board.king_square(BITS_WHITE), Some(*square!("e1")));
+        assert_eq!(board.king_square(BITS_BLACK), Some(*square!("e8")));
+
+        let castle = board.make_move(&Move::new(*square!("e1"), *square!("c1")))?;
+        assert_eq!(board.king_square(BITS_WHITE), Some(*square!("c1")));
+        let king_move = board.make_move(&Move::new(*square!("e8"), *square!("e7")))?;
+        assert_eq!(board.king_square(BITS_BLACK), Some(*square!("e7")));
+
+        board.unmake_move(king_move);
+        board.unmake_move(castle);
+        assert_eq!(board.king_square(BITS_WHITE), Some(*square!("e1")));
+        assert_eq!(board.king_square(BITS_BLACK), Some(*square!("e8")));
+
+        assert_eq!(board.mirror().king_square(BITS_WHITE), Some(*square!("d1")));
+        assert_eq!(
+            board.flip_vertical().king_square(BITS_WHITE),
+            Some(*square!("e1"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn king_square_after_setting_pieces() -> crate::Result<()> {
+        let mut board = fen::import("4k3/8/8/8/8/8/8/4K3 w - - 0 1")?;
+
+        board.set_piece(square!("e1"), BITS_NO_PIECE);
+        board.set_piece(square!("d2"), BITS_WHITE | BITS_KING);
+        assert_eq!(board.king_square(BITS_WHITE), Some(*square!("d2")));
+
+        board.set_piece(square!("e8"), BITS_NO_PIECE);
+        assert_eq!(board.king_square(BITS_BLACK), None);
+        assert!(!board.in_check(BITS_BLACK));
+        Ok(())
+    }
+
+    #[test]
+    fn tracked_pieces_follow_moves() -> crate::Result<()> {
+        fn check_tracking(board: &Board) {
+            let mut recomputed = board.clone();
+            recomputed.track_pieces();
+            assert_eq!(board.occupancy, recomputed.occupancy, "{board:?}");
+            assert_eq!(board.king_squares, recomputed.king_squares, "{board:?}");
+            assert_eq!(board.piece_hash, recomputed.piece_hash, "{board:?}");
+            assert_eq!(board.piece_attacks, recomputed.piece_attacks, "{board:?}");
+        }
+
+        // Castling, en passant and promotions with and without captures are all possible here
+        let mut board = fen::import("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1")?;
+        for mv in board.gen_moves() {
+            let undo = board.make_move(&mv)?;
+            check_tracking(&board);
+            for reply in board.gen_moves() {
+                let reply_undo = board.make_move(&reply)?;
+                check_tracking(&board);
+                board.unmake_move(reply_undo);
+            }
+            board.unmake_move(undo);
+            check_tracking(&board);
+        }
+
+        board.set_piece(square!("e5"), BITS_NO_PIECE);
+        board.set_piece(square!("a1"), BITS_BLACK | BITS_QUEEN);
+        check_tracking(&board);
+        assert_eq!(board.occupancy[color_index(BITS_WHITE)].count_ones(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn undo_token_describes_the_move() -> crate::Result<()> {
+        let mut board = fen::import("r3k3/8/8/3pP3/8/8/8/R3K2R w KQq d6 7 1")?;
+
+        let undo = board.make_move(&Move::new(*square!("e5"), *square!("d6")))?;
+        assert_eq!(undo.mv(), Move::new(*square!("e5"), *square!("d6")));
+        assert_eq!(undo.moved(), BITS_WHITE | BITS_PAWN);
+        assert_eq!(undo.captured(), Some(BITS_BLACK | BITS_PAWN));
+        assert_eq!(undo.captured_square(), Some(*square!("d5")));
+        assert_eq!(undo.en_passant(), Some(*square!("d6")));
+        assert_eq!(
+            undo.castling_rights(),
+            CASTLE_WHITE_KINGSIDE | CASTLE_WHITE_QUEENSIDE | CASTLE_BLACK_QUEENSIDE
+        );
+        assert_eq!(undo.halfmove_clock(), 7);
+        board.unmake_move(undo);
+
+        let undo = board.make_move(&Move::new(*square!("a1"), *square!("a8")))?;
+        assert_eq!(undo.captured(), Some(BITS_BLACK | BITS_ROOK));
+        board.unmake_move(undo);
+
+        let undo = board.make_move(&Move::new(*square!("e1"), *square!("f1")))?;
+        assert_eq!(undo.captured(), None);
+        assert_eq!(undo.captured_square(), None);
+        assert_eq!(board.castling_rights(), CASTLE_BLACK_QUEENSIDE);
+        Ok(())
+    }
+
+    #[test]
+    fn undo_move_restores_the_position() -> crate::Result<()> {
+        let fens = [
+            // Castling both ways
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+            // En passant
+            "7k/8/8/8/pPp5/8/8/7K b - b3 0 1",
+            // Promotions with and without capture
+            "nnnnnnnn/PPPPPPPP/8/8/8/8/8/K6k w - - 0 1",
+            positions::KIWIPETE,
+        ];
+
+        for fen in fens {
+            let mut board = fen::import(fen)?;
+            for mv in board.gen_moves() {
+                board.move_piece(&mv.from, &mv.to, mv.promotion)?;
+                assert_eq!(board.undo_move(), Some(mv));
+                assert_eq!(board.pieces, fen::import(fen)?.pieces);
+                assert_eq!(fen::export(&board), fen::export(&fen::import(fen)?));
+            }
+            assert_eq!(board.undo_move(), None);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn move_piece_applies_the_move() -> crate::Result<()> {
+        let mut board = Board::new();
+        board.move_piece(square!("g1"), square!("f3"), None)?;
+        assert_eq!(
+            fen::export(&board),
+            "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 0"
+        );
+
+        assert!(board
+            .move_piece(square!("e2"), square!("e4"), None)
+            .is_err());
+        board.move_piece(square!("e7"), square!("e5"), None)?;
+        assert_eq!(board.side_to_move(), BITS_WHITE);
+        Ok(())
+    }
+
+    #[test]
+    fn attackers_of() -> crate::Result<()> {
+        let board = fen::import("3r2k1/8/1n6/3p4/8/2N5/8/3Q2K1 w - - 0 1")?;
+        let attackers = |sq: &Square, color| {
+            board
+                .attackers_of(sq, color)
+                .into_iter()
+                .map(|(sq, p)| (sq.to_str(), piece_type(p)))
+                .collect_vec()
+        };
+
+        assert_eq!(
+            attackers(square!("d5"), BITS_WHITE),
+            [
+                ("C3".to_string(), BITS_KNIGHT),
+                ("D1".to_string(), BITS_QUEEN)
+            ]
+        );
+        assert_eq!(
+            attackers(square!("d5"), BITS_BLACK),
+            [
+                ("B6".to_string(), BITS_KNIGHT),
+                ("D8".to_string(), BITS_ROOK)
+            ]
+        );
+        assert_eq!(
+            attackers(square!("e4"), BITS_BLACK),
+            [("D5".to_string(), BITS_PAWN)]
+        );
+        assert!(attackers(square!("h4"), BITS_WHITE).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn is_legal_agrees_with_move_generation() -> crate::Result<()> {
+        let fens = [
+            positions::STARTPOS,
+            positions::KIWIPETE,
+            positions::LUCENA,
+            "r3k2r/8/8/8/pP6/8/8/R3K2R b KQkq b3 0 1",
+            "4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1",
+            "3k4/1P4P1/8/8/8/8/6p1/4K2R w K - 0 1",
+            "3k4/1P4P1/8/8/8/8/6p1/4K2R b K - 0 1",
+        ];
+
+        for fen in fens {
+            let board = fen::import(fen)?;
+            let legal = board.gen_moves();
+
+            let squares = (0..64).map(Square::from_index);
+            for (from, to) in squares.clone().cartesian_product(squares) {
+                let promotions = [None, Some(BITS_QUEEN), Some(BITS_KNIGHT), Some(BITS_KING)];
+                for promotion in promotions {
+                    let mv = Move {
+                        promotion,
+                        ..Move::new(from, to)
+                    };
+                    assert_eq!(board.is_legal(&mv), legal.contains(&mv), "{fen}: {mv:?}");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn pseudo_legal_moves() -> crate::Result<()> {
+        // The rook on e2 is pinned to the king on e1
+        let board = fen::import("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1")?;
+        let pseudo_legal = board.gen_pseudo_legal_moves();
+        let legal = board.gen_moves();
+
+        assert_eq!(pseudo_legal.len(), 12 + 4);
+        assert_eq!(legal.len(), 5 + 4);
+        let illegal = pseudo_legal
+            .iter()
+            .filter(|mv| board.leaves_king_in_check(mv))
+            .collect_vec();
+        assert_eq!(illegal.len(), pseudo_legal.len() - legal.len());
+        assert!(illegal.iter().all(|mv| !legal.contains(mv)));
+        Ok(())
+    }
+
+    #[test]
+    fn legal_moves() -> crate::Result<()> {
+        let board = positions::kiwipete();
+        assert_eq!(board.legal_moves().collect_vec(), board.gen_moves());
+
+        let first = board.legal_moves().next().unwrap();
+        assert!(board.is_legal(&first));
+
+        // Checkmated
+        let board = fen::import("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1")?;
+        assert_eq!(board.legal_moves().next(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn gen_moves_into() {
+        let mut moves = MoveList::new();
+        for board in [positions::kiwipete(), Board::new(), positions::lucena()] {
+            board.gen_moves_into(&mut moves, Promotions::All);
+            assert_eq!(moves.to_vec(), board.gen_moves());
+
+            board.gen_pseudo_legal_moves_into(&mut moves, Promotions::All);
+            assert_eq!(moves.to_vec(), board.gen_pseudo_legal_moves());
+        }
+    }
+
+    #[test]
+    fn queen_only_promotions() -> crate::Result<()> {
+        // A promotion with and without capture
+        let board = fen::import("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1")?;
+        let mut moves = MoveList::new();
+
+        board.gen_moves_into(&mut moves, Promotions::QueenOnly);
+        let all_moves = board.gen_moves();
+        assert_eq!(moves.len(), all_moves.len() - 2 * 3);
+        assert!(moves
+            .iter()
+            .all(|mv| mv.promotion.is_none_or(|p_type| p_type == BITS_QUEEN)));
+        assert_eq!(moves.iter().filter(|mv| mv.is_promotion()).count(), 2);
+        assert!(moves.iter().all(|mv| all_moves.contains(mv)));
+
+        board.gen_pseudo_legal_moves_into(&mut moves, Promotions::QueenOnly);
+        assert_eq!(moves.len(), board.gen_pseudo_legal_moves().len() - 2 * 3);
+        Ok(())
+    }
+
+    #[test]
+    fn unmake_move_does_not_allocate() -> crate::Result<()> {
+        let mut board = positions::kiwipete();
+        let mut moves = MoveList::new();
+        board.gen_moves_into(&mut moves, Promotions::All);
+
+        for mv in moves.iter() {
+            let undo = board.make_move(mv)?;
+            assert_eq!(count_allocations(|| board.unmake_move(undo)), 0);
+        }
+        assert_eq!(count_allocations(|| moves.retain(|mv| mv.is_capture())), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn move_generation_does_not_allocate() -> crate::Result<()> {
+        let mut board = positions::kiwipete();
+        board.move_piece(square!("e1"), square!("f1"), None)?;
+        board.move_piece(square!("e8"), square!("f8"), None)?;
+        let mut moves = MoveList::new();
+
+        assert_eq!(
+            count_allocations(|| board.gen_moves_into(&mut moves, Promotions::All)),
+            0
+        );
+        assert_eq!(count_allocations(|| drop(board.without_history())), 0);
+        assert_eq!(board.without_history(), board);
+        assert_eq!(board.without_history().undo_move(), None);
+        for mv in moves.iter() {
+            let mut undo = None;
+            assert_eq!(count_allocations(|| undo = board.make_move(mv).ok()), 0);
+            board.unmake_move(undo.unwrap());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn staged_moves() -> crate::Result<()> {
+        let board = positions::kiwipete();
+        let staged = board.staged_moves().collect_vec();
+        assert_eq!(staged.len(), board.count_moves());
+        assert!(board.gen_moves().iter().all(|mv| staged.contains(mv)));
+
+        // Captures and promotions first, then the rest
+        let num_noisy = staged
+            .iter()
+            .take_while(|mv| mv.is_capture() || mv.is_promotion())
+            .count();
+        assert_eq!(num_noisy, 8);
+        assert!(staged[num_noisy..]
+            .iter()
+            .all(|mv| !mv.is_capture() && !mv.is_promotion()));
+
+        // Promotions without capture and an en passant capture come before the king moves
+        let board = fen::import("4k3/1P6/8/3pP3/8/8/8/K7 w - d6 0 1")?;
+        let staged = board.staged_moves().collect_vec();
+        assert_eq!(staged[0], Move::new(*square!("e5"), *square!("d6")));
+        assert!(staged[1..5].iter().all(|mv| mv.is_promotion()));
+        assert!(staged[5..]
+            .iter()
+            .all(|mv| mv.from == *square!("a1") || mv.from == *square!("e5")));
+        Ok(())
+    }
+
+    #[test]
+    fn gen_moves_from() -> crate::Result<()> {
+        let board = Board::new();
+        assert_eq!(
+            board.gen_moves_from(square!("g1")),
+            vec![
+                Move::new(*square!("g1"), *square!("f3")),
+                Move::new(*square!("g1"), *square!("h3")),
+            ]
+        );
+        assert!(board.gen_moves_from(square!("e4")).is_empty());
+        assert!(board.gen_moves_from(square!("g8")).is_empty());
+
+        // Pinned pieces may only move along the pin
+        let board = fen::import("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1")?;
+        assert_eq!(board.gen_moves_from(square!("e2")).len(), 5);
+
+        let all = board.gen_moves();
+        for sq in (0..64).map(Square::from_index) {
+            for mv in board.gen_moves_from(&sq) {
+                assert!(all.contains(&mv));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn generated_moves_are_annotated() -> crate::Result<()> {
+        let board = fen::import("r3k3/8/N7/8/pP6/8/8/R3K2R b KQq b3 0 1")?;
+        let find = |moves: &[Move], from: &str, to: &str| {
+            *moves
+                .iter()
+                .find(|mv| *mv == &Move::new(*square!(from), *square!(to)))
+                .unwrap()
+        };
+        let moves = board.gen_moves();
+
+        let en_passant = find(&moves, "a4", "b3");
+        assert!(en_passant.is_en_passant() && en_passant.is_capture());
+        assert_eq!(piece_type(en_passant.moved), BITS_PAWN);
+        assert_eq!(en_passant.captured, BITS_WHITE | BITS_PAWN);
+
+        let capture = find(&moves, "a8", "a6");
+        assert!(capture.is_capture() && !capture.is_en_passant());
+        assert_eq!(piece_type(capture.captured), BITS_KNIGHT);
+
+        let castling = find(&moves, "e8", "c8");
+        assert!(castling.is_castling() && !castling.is_capture());
+        assert_eq!(castling.moved, BITS_BLACK | BITS_KING);
+
+        let quiet = find(&moves, "e8", "d8");
+        assert_eq!(quiet.flags, MOVE_FLAGS_NONE);
+        assert!(!quiet.is_capture() && !quiet.is_promotion());
+
+        let moves = Board::new().gen_moves();
+        assert!(find(&moves, "e2", "e4").is_double_step());
+        assert!(!find(&moves, "e2", "e3").is_double_step());
+        Ok(())
+    }
+
+    #[test]
+    fn en_passant_after_double_step() -> crate::Result<()> {
+        let mut board = fen::import("4k3/8/8/8/1p6/8/P1P5/4K3 w - - 0 1")?;
+
+        board.move_piece(square!("a2"), square!("a4"), None)?;
+        assert_eq!(board.en_passant, Some(*square!("a3")));
+        assert!(board
+            .gen_moves()
+            .contains(&Move::new(*square!("b4"), *square!("a3"))));
+
+        // The right to capture en passant expires after one move
+        board.move_piece(square!("e8"), square!("d8"), None)?;
+        assert_eq!(board.en_passant, None);
+        board.move_piece(square!("c2"), square!("c3"), None)?;
+        assert!(!board
+            .gen_moves()
+            .contains(&Move::new(*square!("b4"), *square!("a3"))));
+
+        board.undo_move();
+        board.undo_move();
+        board.move_piece(square!("b4"), square!("a3"), None)?;
+        assert_eq!(board.get_piece(square!("a4")), BITS_NO_PIECE);
+        assert_eq!(fen::export(&board), "4k3/8/8/8/8/p7/2P5/4K3 w - - 0 0");
+        Ok(())
+    }
+
+    #[test]
+    fn halfmove_clock() -> crate::Result<()> {
+        let mut board = Board::new();
+        assert_eq!(board.halfmove_clock(), 0);
+
+        board.move_piece(square!("g1"), square!("f3"), None)?;
+        board.move_piece(square!("b8"), square!("c6"), None)?;
+        assert_eq!(board.halfmove_clock(), 2);
+
+        // Pawn moves reset the clock
+        board.move_piece(square!("e2"), square!("e4"), None)?;
+        assert_eq!(board.halfmove_clock(), 0);
+
+        board.move_piece(square!("c6"), square!("d4"), None)?;
+        assert_eq!(board.halfmove_clock(), 1);
+
+        // ... and so do captures
+        board.move_piece(square!("f3"), square!("d4"), None)?;
+        assert_eq!(board.halfmove_clock(), 0);
+
+        board.undo_move();
+        assert_eq!(board.halfmove_clock(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn fifty_move_draw() -> crate::Result<()> {
+        let mut board = fen::import("8/8/8/4k3/8/8/8/R3K3 w - - 99 80")?;
+        assert!(!board.is_fifty_move_draw());
+
+        board.move_piece(square!("a1"), square!("a2"), None)?;
+        assert!(board.is_fifty_move_draw());
+
+        // Checkmate on the hundredth halfmove wins the game
+        let mut board = fen::import("4k3/R7/4K3/8/8/8/8/8 w - - 99 80")?;
+        board.move_piece(square!("a7"), square!("a8"), None)?;
+        assert!(board.is_checkmate());
+        assert!(!board.is_fifty_move_draw());
+        Ok(())
+    }
+
+    #[test]
+    fn draw_claim_with_intended_move() -> crate::Result<()> {
+        let mut board = Board::new();
+        let shuffle = ["g1", "f3", "g8", "f6", "f3", "g1", "f6", "g8"];
+        for mv in shuffle.chunks(2).chain(shuffle.chunks(2).take(3)) {
+            board.move_piece(&Square::from(mv[0])?, &Square::from(mv[1])?, None)?;
+        }
+
+        // Moving the knight back to g8 repeats the position a third time
+        let back = Move::new(*square!("f6"), *square!("g8"));
+        assert_eq!(
+            board.draw_claim_with(&back)?,
+            Some(GameStatus::DrawByThreefoldRepetition)
+        );
+        assert_eq!(
+            board.draw_claim_with(&Move::new(*square!("e7"), *square!("e5")))?,
+            None
+        );
+        assert!(board
+            .draw_claim_with(&Move::new(*square!("f6"), *square!("f5")))
+            .is_err());
+        // The move was not played
+        assert_eq!(board.status(), GameStatus::Ongoing);
+        assert_eq!(board.get_piece(square!("f6")), BITS_BLACK | BITS_KNIGHT);
+
+        let board = fen::import("8/8/8/4k3/8/8/8/R3K3 w - - 99 80")?;
+        assert_eq!(
+            board.draw_claim_with(&Move::new(*square!("a1"), *square!("a2")))?,
+            Some(GameStatus::DrawByFiftyMove)
+        );
+        // A pawn move or capture resets the halfmove clock
+        let board = fen::import("8/8/8/4k3/8/8/P7/4K3 w - - 99 80")?;
+        assert_eq!(
+            board.draw_claim_with(&Move::new(*square!("a2"), *square!("a3")))?,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn threefold_repetition() -> crate::Result<()> {
+        let mut board = Board::new();
+        let shuffle = ["g1", "f3", "g8", "f6", "f3", "g1", "f6", "g8"];
+
+        for _ in 0..2 {
+            assert!(!board.is_threefold_repetition());
+            for mv in shuffle.chunks(2) {
+                board.move_piece(&Square::from(mv[0])?, &Square::from(mv[1])?, None)?;
+            }
+        }
+        assert!(board.is_threefold_repetition());
+
+        board.undo_move();
+        assert!(!board.is_threefold_repetition());
+        Ok(())
+    }
+
+    #[test]
+    fn insufficient_material() -> crate::Result<()> {
+        for (fen, insufficient) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/3NK3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/3BK3 b - - 0 1", true),
+            // Bishops on light squares only
+            ("2b1k3/8/8/8/8/8/8/3BKB2 w - - 0 1", true),
+            // Bishops on both colors
+            ("3bk3/8/8/8/8/8/8/3BK3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/2NNK3 w - - 0 1", false),
+            ("4kn2/8/8/8/8/8/8/3BK3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", false),
+        ] {
+            let board = fen::import(fen)?;
+            assert_eq!(board.is_insufficient_material(), insufficient, "{fen}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn game_status() -> crate::Result<()> {
+        let status = |fen: &str| fen::import(fen).map(|board| board.status());
+
+        assert_eq!(Board::new().status(), GameStatus::Ongoing);
+        assert_eq!(
+            status("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1")?,
+            GameStatus::Checkmate(BITS_WHITE)
+        );
+        assert_eq!(
+            status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1")?,
+            GameStatus::Stalemate
+        );
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/3NK3 w - - 0 1")?,
+            GameStatus::DrawByInsufficientMaterial
+        );
+        assert_eq!(
+            status("4k3/8/8/8/8/8/8/R3K3 w - - 100 80")?,
+            GameStatus::DrawByFiftyMove
+        );
+        // Checkmate takes precedence over the fifty-move rule
+        assert_eq!(
+            status("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80")?,
+            GameStatus::Checkmate(BITS_WHITE)
+        );
+
+        let mut board = Board::new();
+        for _ in 0..2 {
+            for mv in ["g1", "f3", "g8", "f6", "f3", "g1", "f6", "g8"].chunks(2) {
+                board.move_piece(&Square::from(mv[0])?, &Square::from(mv[1])?, None)?;
+            }
+        }
+        assert_eq!(board.status(), GameStatus::DrawByThreefoldRepetition);
+        Ok(())
+    }
+
+    #[test]
+    fn repetition_after_double_step() -> crate::Result<()> {
+        let mut board = Board::new();
+        board.move_piece(square!("e2"), square!("e4"), None)?;
+        let after_double_step = board.clone();
+
+        // Black can't capture en passant, so the knight moves repeat the position after e4
+        for _ in 0..2 {
+            assert!(!board.is_threefold_repetition());
+            board.move_piece(square!("g8"), square!("f6"), None)?;
+            board.move_piece(square!("g1"), square!("f3"), None)?;
+            board.move_piece(square!("f6"), square!("g8"), None)?;
+            board.move_piece(square!("f3"), square!("g1"), None)?;
+        }
+        assert_eq!(board.en_passant, None);
+        assert_eq!(board, after_double_step);
+        assert_eq!(board.zobrist_hash(), after_double_step.zobrist_hash());
+        assert!(board.is_threefold_repetition());
+        Ok(())
+    }
+
+    #[test]
+    fn repetition_respects_castling_rights() -> crate::Result<()> {
+        // The rooks return to the same squares, but both sides have lost their castling rights
+        let mut board = fen::import("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1")?;
+        let shuffle = ["a1", "a2", "a8", "a7", "a2", "a1", "a7", "a8"];
+
+        for _ in 0..2 {
+            for mv in shuffle.chunks(2) {
+                board.move_piece(&Square::from(mv[0])?, &Square::from(mv[1])?, None)?;
+            }
+        }
+        // The starting position had other castling rights, so it only occurred twice
+        assert!(!board.is_threefold_repetition());
+
+        for mv in shuffle.chunks(2) {
+            board.move_piece(&Square::from(mv[0])?, &Square::from(mv[1])?, None)?;
+        }
+        assert!(board.is_threefold_repetition());
+        Ok(())
+    }
+
+    #[test]
+    fn phase() -> crate::Result<()> {
+        assert_eq!(Board::new().phase_value(), PHASE_VALUE_MAX);
+        assert_eq!(Board::new().phase(), Phase::Opening);
+
+        let board = fen::import("r1b2rk1/pp3ppp/2n5/8/8/2N5/PP3PPP/R1B2RK1 w - - 0 1")?;
+        assert_eq!(board.phase(), Phase::Middlegame);
+
+        let board = fen::import("8/5k2/8/3r4/8/2K5/5R2/8 w - - 0 1")?;
+        assert_eq!(board.phase(), Phase::Endgame);
+        Ok(())
+    }
+
+    #[test]
+    fn pieces_iter() -> crate::Result<()> {
+        let board = fen::import("4k3/8/8/8/8/8/4P3/R3K3 w Q - 0 1")?;
+        assert_eq!(
+            board.pieces_iter().collect_vec(),
+            vec![
+                (*square!("a1"), BITS_WHITE | BITS_ROOK),
+                (*square!("e1"), BITS_WHITE | BITS_KING),
+                (*square!("e2"), BITS_WHITE | BITS_PAWN),
+                (*square!("e8"), BITS_BLACK | BITS_KING | BITS_HAS_MOVED),
+            ]
+        );
+        assert_eq!(positions::empty().pieces_iter().count(), 0);
+        assert_eq!(Board::new().pieces_iter().count(), 32);
+        Ok(())
+    }
+
+    #[test]
+    fn material() -> crate::Result<()> {
+        let start = Material {
+            pawns: 8,
+            knights: 2,
+            bishops: 2,
+            rooks: 2,
+            queens: 1,
+        };
+        assert_eq!(Board::new().material(BITS_WHITE), start);
+        assert_eq!(Board::new().material(BITS_BLACK), start);
+        assert_eq!(start.value(&PieceValues::CLASSIC), 3900);
+
+        let board = fen::import("4k3/8/8/8/8/8/1q6/R3K3 w - - 0 1")?;
+        assert_eq!(board.material(BITS_WHITE).value(&PieceValues::CLASSIC), 500);
+        assert_eq!(
+            board.material(BITS_BLACK),
+            Material {
+                queens: 1,
+                ..Material::default()
+            }
+        );
+        let values = PieceValues {
+            queen: 10,
+            ..PieceValues::CLASSIC
+        };
+        assert_eq!(board.material(BITS_BLACK).value(&values), 10);
+        Ok(())
+    }
+
+    #[test]
+    fn material_signature() -> crate::Result<()> {
+        assert_eq!(
+            Board::new().material_signature(),
+            "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP"
+        );
+
+        let board = fen::import("3qk3/8/8/8/8/8/8/1R2K1R1 w - - 0 1")?;
+        assert_eq!(board.material_signature(), "KRRvKQ");
+        Ok(())
+    }
+
+    #[test]
+    fn stable_hash() -> crate::Result<()> {
+        // This value must never change, since users may store it
+        assert_eq!(Board::new().stable_hash(), 0x4055bbed8b1a4404);
+        assert_eq!(Board::new().pack(&mut [0; PACKED_MAX_LEN]), 24);
+
+        let hash = |fen: &str| fen::import(fen).map(|b| b.stable_hash());
+        let base = hash("r3k2r/8/8/8/3pP3/8/8/R3K2R w KQkq - 0 1")?;
+        assert_eq!(base, hash("r3k2r/8/8/8/3pP3/8/8/R3K2R w KQkq - 0 1")?);
+        assert_ne!(base, hash("r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq - 0 1")?);
+        assert_ne!(base, hash("r3k2r/8/8/8/3pP3/8/8/R3K2R w KQk - 0 1")?);
+        // The en passant square only counts when the capture is possible
+        assert_eq!(base, hash("r3k2r/8/8/8/3pP3/8/8/R3K2R w KQkq e3 0 1")?);
+        assert_ne!(
+            hash("r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq - 0 1")?,
+            hash("r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq e3 0 1")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn to_ascii() {
+        assert_eq!(
+            Board::new().to_ascii(),
+            "\
+8 r n b q k b n r
+7 p p p p p p p p
+6 . . . . . . . .
+5 . . . . . . . .
+4 . . . . . . . .
+3 . . . . . . . .
+2 P P P P P P P P
+1 R N B Q K B N R
+  a b c d e f g h
+"
+        );
+    }
+
+    #[test]
+    fn to_unicode() -> crate::Result<()> {
+        let board = fen::import("4k3/8/8/8/8/8/4P3/R3K3 w Q - 0 1")?;
+        assert_eq!(
+            board.to_unicode(BITS_WHITE),
+            "\
+8 · · · · ♚ · · ·
+7 · · · · · · · ·
+6 · · · · · · · ·
+5 · · · · · · · ·
+4 · · · · · · · ·
+3 · · · · · · · ·
+2 · · · · ♙ · · ·
+1 ♖ · · · ♔ · · ·
+  a b c d e f g h
+"
+        );
+        assert_eq!(
+            board.to_unicode(BITS_BLACK),
+            "\
+1 · · · ♔ · · · ♖
+2 · · · ♙ · · · ·
+3 · · · · · · · ·
+4 · · · · · · · ·
+5 · · · · · · · ·
+6 · · · · · · · ·
+7 · · · · · · · ·
+8 · · · ♚ · · · ·
+  h g f e d c b a
+"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn mirror() -> crate::Result<()> {
+        let board = fen::import("r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq e3 4 1")?;
+        assert_eq!(
+            fen::export(&board.mirror()),
+            "r2k3r/8/8/8/3Pp3/8/8/R2K3R b - d3 4 0"
+        );
+        // Mirroring twice gives the same position, but without castling rights
+        assert_eq!(
+            fen::export(&board.mirror().mirror()),
+            "r3k2r/8/8/8/3pP3/8/8/R3K2R b - e3 4 0"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn flip_vertical() -> crate::Result<()> {
+        let board = fen::import("r3k3/8/8/8/3pP3/8/8/R3K2R b KQq e3 4 1")?;
+        let flipped = board.flip_vertical();
+        assert_eq!(
+            fen::export(&flipped),
+            "r3k2r/8/8/3Pp3/8/8/8/R3K3 w Qkq e6 4 0"
+        );
+        assert_eq!(flipped.flip_vertical(), board);
+
+        // The flipped position has the same moves, seen from the other side
+        for fen in [
+            positions::STARTPOS,
+            positions::KIWIPETE,
+            "r3k3/8/8/8/3pP3/8/8/R3K2R b KQq e3 0 1",
+        ] {
+            let board = fen::import(fen)?;
+            let flipped = board.flip_vertical();
+            assert_eq!(flipped.count_moves(), board.count_moves(), "{fen}");
+            assert_eq!(
+                flipped.in_check_to_move(),
+                board.in_check_to_move(),
+                "{fen}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn debug_diagram() -> crate::Result<()> {
+        let mut board = fen::import("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1")?;
+        board.move_piece(square!("e2"), square!("e4"), None)?;
+
+        let diagram = "\
+8 . . . . k . . .
+7 . . . . . . . .
+6 . . . . . . . .
+5 . . . . . . . .
+4 . . . . P . . .
+3 . . . . . . . .
+2 . . . . . . . .
+1 . . . . K . . .
+  a b c d e f g h
+4k3/8/8/8/4P3/8/8/4K3 b - e3 0 0";
+        assert_eq!(format!("{board:?}"), diagram);
+
+        let parsed = Board::try_from(diagram)?;
+        assert_eq!(fen::export(&parsed), fen::export(&board));
+
+        // Indented, as when pasted into a test
+        let indented: String = diagram.lines().map(|l| format!("    {l}\n")).collect();
+        assert!(Board::try_from(indented.as_str()).is_ok());
+
+        let kiwipete = positions::kiwipete();
+        let parsed = Board::try_from(format!("{kiwipete:?}").as_str())?;
+        assert_eq!(fen::export(&parsed), fen::export(&kiwipete));
+        Ok(())
+    }
+
+    #[test]
+    fn debug_diagram_must_match_the_fen_string() {
+        assert!(Board::try_from("").is_err());
+        assert!(Board::try_from("not a fen string").is_err());
+        // No diagram
+        assert!(Board::try_from("4k3/8/8/8/8/8/8/4K3 w - - 0 1").is_err());
+
+        let wrong_piece = "\
+8 . . . . k . . .
+7 . . . . . . . .
+6 . . . . . . . .
+5 . . . . . . . .
+4 . . . . . . . .
+3 . . . . . . . .
+2 . . . . . . . .
+1 . . . Q K . . .
+  a b c d e f g h
+4k3/8/8/8/8/8/8/4K3 w - - 0 1";
+        let err = Board::try_from(wrong_piece).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Diagram line \"1 . . . Q K . . .\" doesn't match the FEN string \
+             (expected \"1 . . . . K . . .\")"
+        );
+    }
+
+    #[test]
+    fn zobrist_hash() -> crate::Result<()> {
+        use std::collections::HashSet;
+
+        // The same position reached by different move orders
+        let mut board = Board::new();
+        let mut transposed = Board::new();
+        for (from, to) in [("g1", "f3"), ("g8", "f6"), ("b1", "c3")] {
+            board.move_piece(&Square::from(from)?, &Square::from(to)?, None)?;
+        }
+        for (from, to) in [("b1", "c3"), ("g8", "f6"), ("g1", "f3")] {
+            transposed.move_piece(&Square::from(from)?, &Square::from(to)?, None)?;
+        }
+        assert_eq!(board.zobrist_hash(), transposed.zobrist_hash());
+        assert_ne!(board.zobrist_hash(), Board::new().zobrist_hash());
+
+        // Side to move, castling rights and en passant square count
+        let hash = |fen| fen::import(fen).map(|board| board.zobrist_hash());
+        let fen = "r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1";
+        assert_ne!(
+            hash(fen)?,
+            hash("r3k2r/8/8/3pP3/8/8/8/R3K2R b KQkq d6 0 1")?
+        );
+        assert_ne!(hash(fen)?, hash("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQk d6 0 1")?);
+        assert_ne!(hash(fen)?, hash("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq - 0 1")?);
+        // The clocks don't
+        assert_eq!(
+            hash(fen)?,
+            hash("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 12 40")?
+        );
+
+        // No collisions among the positions after each move. The hashes kept up to date by the
+        // moves agree with the ones of the same positions set up from scratch.
+        let mut board = positions::kiwipete();
+        let hash_before = board.zobrist_hash();
+        let mut hashes = HashSet::new();
+        for mv in board.gen_moves() {
+            let undo = board.make_move(&mv)?;
+            let hash = board.zobrist_hash();
+            assert_eq!(hash, fen::import(&fen::export(&board))?.zobrist_hash());
+            assert!(hashes.insert(hash));
+
+            board.unmake_move(undo);
+            assert_eq!(board.zobrist_hash(), hash_before);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn equality_and_hashing() -> crate::Result<()> {
+        use std::collections::HashSet;
+
+        let mut board = Board::new();
+        board.move_piece(square!("g1"), square!("f3"), None)?;
+        board.move_piece(square!("g8"), square!("f6"), None)?;
+        board.move_piece(square!("f3"), square!("g1"), None)?;
+        board.move_piece(square!("f6"), square!("g8"), None)?;
+        // Different clock and history, same position
+        assert_eq!(board, Board::new());
+        assert_eq!(Board::default(), Board::new());
+
+        let positions: HashSet<Board> = [
+            Board::new(),
+            board,
+            fen::import("r3k2r/8/8/8/3pP3/8/8/R3K2R w KQkq - 0 1")?,
+            fen::import("r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq - 0 1")?,
+            fen::import("r3k2r/8/8/8/3pP3/8/8/R3K2R w KQk - 0 1")?,
+            fen::import("r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq e3 0 1")?,
+            fen::import("r3k2r/8/8/8/3pP3/8/8/R3K2R b KQkq e3 5 30")?,
+            // Capturing on d6 would expose the white king to the rook
+            fen::import("4k3/8/8/K2pP2r/8/8/8/8 w - d6 0 1")?,
+            fen::import("4k3/8/8/K2pP2r/8/8/8/8 w - - 0 1")?,
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(positions.len(), 6);
+        Ok(())
+    }
+
+    #[test]
+    fn equality_without_kings() {
+        use std::collections::HashSet;
+
+        let mut black_to_move = positions::empty();
+        black_to_move.make_null_move();
+        assert_ne!(positions::empty(), black_to_move);
+
+        let positions: HashSet<Board> = [positions::empty(), black_to_move.clone()]
+            .into_iter()
+            .collect();
+        assert_eq!(positions.len(), 2);
+
+        black_to_move.make_null_move();
+        assert_eq!(positions::empty(), black_to_move);
+        assert!(positions.contains(&black_to_move));
+    }
+
+    #[test]
+    fn promotion_piece_must_be_chosen() -> crate::Result<()> {
+        let board = fen::import("1k6/5P2/8/8/8/8/8/4K3 w - - 0 1")?;
+        let (from, to) = (square!("f7"), square!("f8"));
+
+        assert!(board.clone().move_piece(from, to, None).is_err());
+        assert!(board.clone().move_piece(from, to, Some(BITS_KING)).is_err());
+        for p_type in PROMOTION_PIECES {
+            let mut after = board.clone();
+            after.move_piece(from, to, Some(p_type))?;
+            assert_eq!(piece_type(after.get_piece(to)), p_type);
+            assert_eq!(piece_color(after.get_piece(to)), BITS_WHITE);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn in_check() -> crate::Result<()> {
+        let board = fen::import("4k3/8/8/8/8/8/8/R3K2r w - - 0 1")?;
+        assert!(board.in_check(BITS_WHITE));
+        assert!(board.in_check_to_move());
+        assert!(!board.in_check(BITS_BLACK));
+
+        assert!(!Board::new().in_check_to_move());
+        assert!(!positions::empty().in_check(BITS_WHITE));
+        Ok(())
+    }
+
+    #[test]
+    fn attacked_squares() -> crate::Result<()> {
+        let board = fen::import("7k/8/8/8/8/8/1p6/N6K w - - 0 1")?;
+        let squares = |bb| bitboard::squares(bb).map(|sq| sq.to_str()).collect_vec();
+
+        assert_eq!(
+            squares(board.attacked_squares(BITS_WHITE)),
+            ["G1", "C2", "G2", "H2", "B3"]
+        );
+        assert_eq!(
+            squares(board.attacked_squares(BITS_BLACK)),
+            ["A1", "C1", "G7", "H7", "G8"]
+        );
+
+        // The starting position; every square of the first three ranks but the corners
+        let attacked = Board::new().attacked_squares(BITS_WHITE);
+        assert_eq!(
+            attacked,
+            (bitboard::RANK_1 | bitboard::RANK_2 | bitboard::RANK_3)
+                & !bitboard::from_square(square!("a1"))
+                & !bitboard::from_square(square!("h1"))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn checkmate() -> crate::Result<()> {
+        // Fool's mate
+        let board = fen::import("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")?;
+        assert!(board.is_checkmate());
+
+        // Check that can be blocked
+        let board = fen::import("rnb1kbnr/pppp1ppp/8/4p3/7q/5P2/PPPPP1PP/RNBQKBNR w KQkq - 1 3")?;
+        assert!(!board.is_checkmate());
+
+        // Stalemate is not checkmate
+        let board = fen::import("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")?;
+        assert!(!board.is_checkmate());
+
+        assert!(!Board::new().is_checkmate());
+        Ok(())
+    }
+
+    #[test]
+    fn no_legal_moves_in_stalemate() -> crate::Result<()> {
+        let board = fen::import("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")?;
+        assert_eq!(board.count_moves(), 0);
+        assert!(!board.has_any_legal_move());
+        Ok(())
+    }
+
+    #[test]
+    fn stalemate() -> crate::Result<()> {
+        let board = fen::import("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1")?;
+        assert!(board.is_stalemate());
+
+        // A pawn that can move prevents stalemate, but a blocked one doesn't
+        let board = fen::import("k7/P7/1K6/8/7p/8/8/8 b - - 0 1")?;
+        assert!(!board.is_stalemate());
+        let board = fen::import("k7/P7/1K6/7p/7P/8/8/8 b - - 0 1")?;
+        assert!(board.is_stalemate());
+
+        // Checkmate is not stalemate
+        let board = fen::import("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")?;
+        assert!(!board.is_stalemate());
+
+        assert!(!Board::new().is_stalemate());
+        Ok(())
+    }
 }
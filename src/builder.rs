@@ -0,0 +1,155 @@
+use crate::bitboard;
+use crate::board::{Board, CastlingRights, CASTLE_NONE};
+use crate::fen;
+use crate::piece::{Color, Piece, BITS_HAS_MOVED, BITS_NO_PIECE, BITS_WHITE};
+use crate::square::Square;
+use crate::Result;
+
+/// Set up a position piece by piece, as an alternative to writing a FEN string.
+///
+/// Like a FEN string, the builder says nothing about which kings and rooks have moved. A king or
+/// rook is considered unmoved if it stands on its home square and is needed for one of the
+/// castling rights given with `castling_rights`.
+#[derive(Clone)]
+pub struct BoardBuilder {
+    pieces: [[Piece; 8]; 8],
+    side_to_move: Color,
+    castling_rights: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u32,
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardBuilder {
+    /// Start from an empty board with white to move and no castling rights.
+    pub fn new() -> Self {
+        BoardBuilder {
+            pieces: [[BITS_NO_PIECE; 8]; 8],
+            side_to_move: BITS_WHITE,
+            castling_rights: CASTLE_NONE,
+            en_passant: None,
+            halfmove_clock: 0,
+        }
+    }
+
+    /// Put `piece` on `sq`, replacing any piece already there. `BITS_NO_PIECE` clears the square.
+    pub fn piece(mut self, sq: &Square, piece: Piece) -> Self {
+        self.pieces[sq.0][sq.1] = piece & !BITS_HAS_MOVED;
+        self
+    }
+
+    pub fn side_to_move(mut self, color: Color) -> Self {
+        self.side_to_move = color;
+        self
+    }
+
+    pub fn castling_rights(mut self, rights: CastlingRights) -> Self {
+        self.castling_rights = rights;
+        self
+    }
+
+    pub fn get_castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
+
+    pub fn en_passant(mut self, sq: Option<Square>) -> Self {
+        self.en_passant = sq;
+        self
+    }
+
+    pub fn halfmove_clock(mut self, halfmove_clock: u32) -> Self {
+        self.halfmove_clock = halfmove_clock;
+        self
+    }
+
+    /// Create the board. Returns an error if the castling rights are not possible with the kings
+    /// and rooks on the board, or if the position could not occur in a game (see
+    /// `Board::validate`).
+    pub fn build(&self) -> Result<Board> {
+        let mut pieces = self.pieces;
+        fen::mark_moved_pieces(self.castling_rights, &mut pieces);
+
+        let mut board = Board {
+            pieces,
+            side_to_move: self.side_to_move,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            history: Vec::new(),
+            position_hashes: Vec::new(),
+            king_squares: [None; 2],
+            occupancy: [bitboard::EMPTY; 2],
+            piece_hash: 0,
+            piece_attacks: [bitboard::EMPTY; 64],
+        };
+        board.track_pieces();
+        fen::check_castling_ability(self.castling_rights, &board)?;
+        board.validate()?;
+
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{CASTLE_BLACK_KINGSIDE, CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE};
+    use crate::piece::{BITS_BLACK, BITS_KING, BITS_PAWN, BITS_ROOK};
+    use crate::square::square;
+
+    fn kings() -> BoardBuilder {
+        BoardBuilder::new()
+            .piece(square!("e1"), BITS_WHITE | BITS_KING)
+            .piece(square!("e8"), BITS_BLACK | BITS_KING)
+    }
+
+    #[test]
+    fn build() -> Result<()> {
+        let board = kings()
+            .piece(square!("a1"), BITS_WHITE | BITS_ROOK)
+            .piece(square!("h1"), BITS_WHITE | BITS_ROOK)
+            .piece(square!("d4"), BITS_BLACK | BITS_PAWN)
+            .piece(square!("e4"), BITS_WHITE | BITS_PAWN)
+            .side_to_move(BITS_BLACK)
+            .castling_rights(CASTLE_WHITE_QUEENSIDE)
+            .en_passant(Some(*square!("e3")))
+            .halfmove_clock(0)
+            .build()?;
+        assert_eq!(fen::export(&board), "4k3/8/8/8/3pP3/8/8/R3K2R b Q e3 0 0");
+        Ok(())
+    }
+
+    #[test]
+    fn pieces_can_be_replaced_and_removed() -> Result<()> {
+        let board = kings()
+            .piece(square!("d1"), BITS_WHITE | BITS_ROOK)
+            .piece(square!("d1"), BITS_WHITE | BITS_PAWN | BITS_HAS_MOVED)
+            .piece(square!("d1"), BITS_NO_PIECE)
+            .build()?;
+        assert_eq!(fen::export(&board), "4k3/8/8/8/8/8/8/4K3 w - - 0 0");
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_positions_are_rejected() {
+        assert!(BoardBuilder::new().build().is_err());
+        assert!(kings()
+            .castling_rights(CASTLE_WHITE_KINGSIDE)
+            .build()
+            .is_err());
+        assert!(kings()
+            .piece(square!("h8"), BITS_BLACK | BITS_ROOK)
+            .castling_rights(CASTLE_BLACK_KINGSIDE)
+            .build()
+            .is_ok());
+        assert!(kings()
+            .piece(square!("e2"), BITS_WHITE | BITS_ROOK)
+            .build()
+            .is_err());
+        assert!(kings().en_passant(Some(*square!("d6"))).build().is_err());
+    }
+}
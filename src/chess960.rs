@@ -0,0 +1,154 @@
+use crate::board::Board;
+use crate::fen;
+
+/// Number of Chess960 starting positions.
+pub const NUM_POSITIONS: usize = 960;
+
+/// Number of the standard starting position.
+pub const STANDARD_POSITION: usize = 518;
+
+/// The Chess960 starting position with number `n`, according to the standard numbering scheme
+/// (Scharnagl's). White is to move.
+///
+/// The crate only supports castling with the king on the e-file and the rooks in the corners, so
+/// the positions have no castling rights except for the standard starting position.
+///
+/// # Panics
+///
+/// Panics if `n` is not less than `NUM_POSITIONS`.
+pub fn nth(n: usize) -> Board {
+    let back_rank = back_rank(n);
+    let fen = format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1",
+        back_rank.to_lowercase(),
+        back_rank
+    );
+
+    fen::import(&fen).expect("Chess960 FEN strings are valid")
+}
+
+/// The number of the Chess960 starting position with the same pieces as `board`, or None if the
+/// pieces of `board` are not in a Chess960 starting position. Only the pieces are compared.
+pub fn number_of(board: &Board) -> Option<usize> {
+    let export = fen::export(board);
+    let placement = export.split(' ').next()?;
+    let rank: Vec<char> = placement.split('/').next_back()?.chars().collect();
+    if rank.len() != 8 {
+        return None;
+    }
+
+    // Undo the steps of `back_rank`
+    let light_bishop = (0..4).find(|i| rank[i * 2 + 1] == 'B')?;
+    let dark_bishop = (0..4).find(|i| rank[i * 2] == 'B')?;
+    let rest: Vec<char> = rank.iter().copied().filter(|p| *p != 'B').collect();
+    let queen = rest.iter().position(|p| *p == 'Q')?;
+    let rest: Vec<char> = rest.into_iter().filter(|p| *p != 'Q').collect();
+    let knights: Vec<usize> = (0..rest.len()).filter(|i| rest[*i] == 'N').collect();
+    let knights = KNIGHTS.iter().position(|k| [k.0, k.1] == knights[..])?;
+
+    let n = ((knights * 6 + queen) * 4 + dark_bishop) * 4 + light_bishop;
+    let back_rank = back_rank(n);
+    let expected = format!(
+        "{}/pppppppp/8/8/8/8/PPPPPPPP/{}",
+        back_rank.to_lowercase(),
+        back_rank
+    );
+
+    (placement == expected).then_some(n)
+}
+
+/// Positions of the knights among the empty squares left after the bishops and the queen are
+/// placed, in the order of the numbering scheme.
+const KNIGHTS: [(usize, usize); 10] = [
+    (0, 1),
+    (0, 2),
+    (0, 3),
+    (0, 4),
+    (1, 2),
+    (1, 3),
+    (1, 4),
+    (2, 3),
+    (2, 4),
+    (3, 4),
+];
+
+/// White's pieces on the first rank of position `n`, from the a-file to the h-file, e.g.
+/// "RNBQKBNR" for the standard starting position.
+fn back_rank(n: usize) -> String {
+    assert!(n < NUM_POSITIONS, "Invalid Chess960 position number {n}");
+
+    // Put a piece on the square with index `idx` among the empty squares
+    fn place_on_empty(rank: &mut [char; 8], idx: usize, piece: char) {
+        let file = (0..8).filter(|f| rank[*f] == ' ').nth(idx).unwrap();
+        rank[file] = piece;
+    }
+
+    let mut rank = [' '; 8];
+
+    // Light-squared bishop on b, d, f or h and dark-squared bishop on a, c, e or g
+    rank[n % 4 * 2 + 1] = 'B';
+    rank[n / 4 % 4 * 2] = 'B';
+    let n = n / 16;
+
+    place_on_empty(&mut rank, n % 6, 'Q');
+    let (first, second) = KNIGHTS[n / 6];
+    // The second knight is placed after the first, which takes up one of the empty squares
+    place_on_empty(&mut rank, first, 'N');
+    place_on_empty(&mut rank, second - 1, 'N');
+
+    // The king goes between the rooks on the three remaining squares
+    for piece in ['R', 'K', 'R'] {
+        place_on_empty(&mut rank, 0, piece);
+    }
+
+    rank.iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::positions;
+
+    #[test]
+    fn known_positions() {
+        assert_eq!(back_rank(0), "BBQNNRKR");
+        assert_eq!(back_rank(STANDARD_POSITION), "RNBQKBNR");
+        assert_eq!(back_rank(959), "RKRNNQBB");
+
+        assert_eq!(
+            fen::export(&nth(STANDARD_POSITION)),
+            fen::export(&positions::startpos())
+        );
+        assert_eq!(
+            fen::export(&nth(0)),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w - - 0 0"
+        );
+    }
+
+    #[test]
+    fn numbering_round_trip() {
+        for n in 0..NUM_POSITIONS {
+            let board = nth(n);
+            assert_eq!(number_of(&board), Some(n));
+            assert!(board.validate().is_ok(), "{n}");
+        }
+    }
+
+    #[test]
+    fn other_positions_have_no_number() {
+        assert_eq!(number_of(&positions::kiwipete()), None);
+        assert_eq!(number_of(&positions::empty()), None);
+        // Rooks and king in the wrong order
+        let board = fen::import("krrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/KRRNNQBB w - - 0 1").unwrap();
+        assert_eq!(number_of(&board), None);
+        // A Chess960 back rank, but a pawn has moved
+        let board = fen::import("bbqnnrkr/pppppppp/8/8/4P3/8/PPPP1PPP/BBQNNRKR w - - 0 1").unwrap();
+        assert_eq!(number_of(&board), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn numbers_are_limited() {
+        nth(NUM_POSITIONS);
+    }
+}
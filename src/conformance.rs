@@ -0,0 +1,273 @@
+use serde::Deserialize;
+
+use crate::fen::{self, compare_fen, FenComparator};
+use crate::Result;
+
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+/// A suite of move generation test cases, as stored in the JSON files of `test_cases/`.
+///
+/// The format is:
+///
+/// ```json
+/// {
+///   "description": "Optional description of the suite",
+///   "testCases": [
+///     {
+///       "start": { "description": "Optional description", "fen": "<FEN string>" },
+///       "num_valid_moves": 20,
+///       "num_pseudo_legal_moves": 20,
+///       "expected": [ { "move": "Na3", "fen": "<FEN string after the move>" } ],
+///       "status": "ongoing"
+///     }
+///   ]
+/// }
+/// ```
+///
+/// `testcases` is accepted as an alias of `testCases`. All fields of a test case but `start` are
+/// optional. If `num_valid_moves` is left out, the number of legal moves is expected to be the
+/// length of `expected`. `num_pseudo_legal_moves` is the number of moves before moves that leave
+/// the king in check are filtered out (see `Board::gen_pseudo_legal_moves`). `status` is one of
+/// `"ongoing"`, `"checkmate"` and `"stalemate"`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Suite {
+    pub description: Option<String>,
+    #[serde(rename = "testCases", alias = "testcases")]
+    pub test_cases: Vec<TestCase>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TestCase {
+    pub start: Start,
+    pub num_valid_moves: Option<usize>,
+    pub num_pseudo_legal_moves: Option<usize>,
+    pub expected: Option<Vec<Expected>>,
+    pub status: Option<Status>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Start {
+    pub description: Option<String>,
+    pub fen: String,
+}
+
+/// A position expected to be reachable with one legal move.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Expected {
+    /// The move in any notation. It is only used to describe failures.
+    #[serde(rename = "move")]
+    pub mov: String,
+    pub fen: String,
+}
+
+/// Whether the game is over in the start position of a test case.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ongoing,
+    Checkmate,
+    Stalemate,
+}
+
+/// A test case that didn't pass.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Failure {
+    /// Description of the suite and the test case
+    pub test_case: String,
+    pub message: String,
+}
+
+/// Read a suite from a JSON file.
+pub fn load(path: &str) -> Result<Suite> {
+    let file = File::open(path)?;
+    let mut buf_reader = BufReader::new(file);
+
+    let mut content = String::new();
+    buf_reader.read_to_string(&mut content)?;
+
+    parse(&content)
+}
+
+/// Read a suite from a JSON string.
+pub fn parse(json: &str) -> Result<Suite> {
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Check the number of legal and pseudo-legal moves and the status of the start position of every
+/// test case.
+/// Returns an error if a start position is not a valid FEN string.
+pub fn check_moves(suite: &Suite) -> Result<Vec<Failure>> {
+    let mut failures = Vec::new();
+
+    for test_case in &suite.test_cases {
+        let board = fen::import(&test_case.start.fen)?;
+        let mut fail = |message: String| failures.push(failure(suite, test_case, message));
+
+        let expected_num_moves = test_case
+            .num_valid_moves
+            .or(test_case.expected.as_ref().map(|expected| expected.len()));
+        if let Some(expected_num_moves) = expected_num_moves {
+            let num_moves = board.gen_moves().len();
+            if num_moves != expected_num_moves {
+                fail(format!(
+                    "{num_moves} legal moves were generated, expected {expected_num_moves}"
+                ));
+            }
+            if board.count_moves() != expected_num_moves {
+                fail(format!(
+                    "count_moves returned {}, expected {expected_num_moves}",
+                    board.count_moves()
+                ));
+            }
+            if board.has_any_legal_move() != (expected_num_moves > 0) {
+                fail(format!(
+                    "has_any_legal_move returned {}",
+                    board.has_any_legal_move()
+                ));
+            }
+        }
+
+        if let Some(expected_num_moves) = test_case.num_pseudo_legal_moves {
+            let num_moves = board.gen_pseudo_legal_moves().len();
+            if num_moves != expected_num_moves {
+                fail(format!(
+                    "{num_moves} pseudo-legal moves were generated, expected {expected_num_moves}"
+                ));
+            }
+        }
+
+        if let Some(expected_status) = test_case.status {
+            let status = if board.is_checkmate() {
+                Status::Checkmate
+            } else if board.is_stalemate() {
+                Status::Stalemate
+            } else {
+                Status::Ongoing
+            };
+            if status != expected_status {
+                fail(format!(
+                    "Status is {status:?}, expected {expected_status:?}"
+                ));
+            }
+        }
+    }
+
+    Ok(failures)
+}
+
+/// Apply every legal move of each test case's start position and compare the resulting
+/// positions with the expected ones. Only the FEN fields selected by `comparator` are compared.
+/// Test cases without expected positions are skipped.
+pub fn check_resulting_positions(suite: &Suite, comparator: FenComparator) -> Result<Vec<Failure>> {
+    let mut failures = Vec::new();
+
+    for test_case in &suite.test_cases {
+        let Some(expected) = &test_case.expected else {
+            continue;
+        };
+
+        let board = fen::import(&test_case.start.fen)?;
+        let mut resulting_fens = Vec::new();
+        for mv in board.gen_moves() {
+            let mut after = board.without_history();
+            after.make_move(&mv)?;
+            resulting_fens.push(fen::export(&after));
+        }
+
+        for exp in expected {
+            let found = resulting_fens
+                .iter()
+                .any(|fen| compare_fen(&exp.fen, fen, comparator).unwrap_or(false));
+            if !found {
+                failures.push(failure(
+                    suite,
+                    test_case,
+                    format!(
+                        "No move resulted in \"{}\" (expected move {})",
+                        exp.fen, exp.mov
+                    ),
+                ));
+            }
+        }
+        if resulting_fens.len() != expected.len() {
+            failures.push(failure(
+                suite,
+                test_case,
+                format!(
+                    "{} moves were generated, expected {}",
+                    resulting_fens.len(),
+                    expected.len()
+                ),
+            ));
+        }
+    }
+
+    Ok(failures)
+}
+
+fn failure(suite: &Suite, test_case: &TestCase, message: String) -> Failure {
+    let suite_description = suite.description.as_deref().unwrap_or("Unnamed test cases");
+    let test_case_description = test_case
+        .start
+        .description
+        .as_deref()
+        .unwrap_or("Unnamed test case");
+
+    Failure {
+        test_case: format!(
+            "Test case \"{test_case_description}\" of test suite \"{suite_description}\""
+        ),
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::CMP_POS;
+
+    #[test]
+    fn failures_are_reported() -> Result<()> {
+        let suite = parse(
+            r#"{
+                "description": "Broken suite",
+                "testCases": [
+                    {
+                        "start": { "description": "Start", "fen": "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1" },
+                        "num_valid_moves": 21,
+                        "num_pseudo_legal_moves": 20,
+                        "status": "stalemate"
+                    },
+                    {
+                        "start": { "fen": "7k/8/8/8/8/8/8/K7 w - - 0 1" },
+                        "expected": [ { "move": "Kb1", "fen": "7k/8/8/8/8/8/8/1K6 b - - 1 1" } ]
+                    }
+                ]
+            }"#,
+        )?;
+
+        let failures = check_moves(&suite)?;
+        assert_eq!(failures.len(), 5);
+        assert_eq!(
+            failures[0].test_case,
+            "Test case \"Start\" of test suite \"Broken suite\""
+        );
+        assert_eq!(failures[2].message, "Status is Ongoing, expected Stalemate");
+
+        // The king has three moves, but only one is listed
+        let failures = check_resulting_positions(&suite, CMP_POS)?;
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].message, "3 moves were generated, expected 1");
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_suites_are_rejected() {
+        assert!(parse(r#"{ "testCases": [ { "num_valid_moves": 1 } ] }"#).is_err());
+        assert!(
+            parse(r#"{ "testCases": [ { "start": { "fen": "x" }, "status": "won" } ] }"#).is_err()
+        );
+        assert!(load("test_cases/does_not_exist.json").is_err());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::board::{
+    CastlingRights, CASTLE_BLACK_KINGSIDE, CASTLE_BLACK_QUEENSIDE, CASTLE_NONE,
+    CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE,
+};
+use crate::builder::BoardBuilder;
+use crate::error::chess_error;
+use crate::fen;
+use crate::piece::{Piece, BITS_BLACK, BITS_NO_PIECE, BITS_WHITE};
+use crate::square::Square;
+use crate::Result;
+
+/// Apply one position setup command to `builder`. The commands are:
+///
+/// * `put <piece> <square>`: put a piece on a square, e.g. `put wQ d4` or `put bp e7`. The piece
+///   is written as a color (`w` or `b`) followed by a piece letter.
+/// * `remove <square>`: remove the piece on a square, if any.
+/// * `clear`: remove all pieces.
+/// * `turn <w|b>`: set the side to move.
+/// * `castling <rights>`: grant (`+`) or revoke (`-`) castling rights, e.g. `castling K- q+`.
+///   Each right is written as in FEN. `castling -` revokes all rights.
+/// * `ep <square|->`: set or clear the en passant square.
+///
+/// Commands and their arguments are separated by whitespace and are case sensitive, since case
+/// tells the castling rights apart. The builder is only changed if the command is valid.
+pub fn execute(builder: &mut BoardBuilder, command: &str) -> Result<()> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+
+    let updated = match words.as_slice() {
+        ["put", piece, sq] => builder
+            .clone()
+            .piece(&Square::from(sq)?, parse_piece(piece)?),
+        ["remove", sq] => builder.clone().piece(&Square::from(sq)?, BITS_NO_PIECE),
+        ["clear"] => {
+            let mut cleared = builder.clone();
+            for sq in (0..64).map(Square::from_index) {
+                cleared = cleared.piece(&sq, BITS_NO_PIECE);
+            }
+            cleared
+        }
+        ["turn", "w"] => builder.clone().side_to_move(BITS_WHITE),
+        ["turn", "b"] => builder.clone().side_to_move(BITS_BLACK),
+        ["castling", "-"] => builder.clone().castling_rights(CASTLE_NONE),
+        ["castling", changes @ ..] if !changes.is_empty() => {
+            let mut rights = builder.get_castling_rights();
+            for change in changes {
+                rights = apply_castling_change(rights, change)?;
+            }
+            builder.clone().castling_rights(rights)
+        }
+        ["ep", "-"] => builder.clone().en_passant(None),
+        ["ep", sq] => builder.clone().en_passant(Some(Square::from(sq)?)),
+        [] => return Err(chess_error("Empty command")),
+        _ => return Err(chess_error(&format!("Invalid command \"{command}\""))),
+    };
+    *builder = updated;
+
+    Ok(())
+}
+
+/// Parse a piece written as a color followed by a piece letter, e.g. "wQ".
+fn parse_piece(s: &str) -> Result<Piece> {
+    let mut chars = s.chars();
+    let (Some(color), Some(letter), None) = (chars.next(), chars.next(), chars.next()) else {
+        return Err(chess_error(&format!("Invalid piece \"{s}\"")));
+    };
+
+    match color {
+        'w' => fen::import_piece(letter.to_ascii_uppercase()),
+        'b' => fen::import_piece(letter.to_ascii_lowercase()),
+        _ => Err(chess_error(&format!("Invalid piece color '{color}'"))),
+    }
+}
+
+/// Apply a change like "K-" or "q+" to `rights`.
+fn apply_castling_change(rights: CastlingRights, change: &str) -> Result<CastlingRights> {
+    let right = match change.chars().next() {
+        Some('K') => CASTLE_WHITE_KINGSIDE,
+        Some('Q') => CASTLE_WHITE_QUEENSIDE,
+        Some('k') => CASTLE_BLACK_KINGSIDE,
+        Some('q') => CASTLE_BLACK_QUEENSIDE,
+        _ => {
+            return Err(chess_error(&format!(
+                "Invalid castling change \"{change}\""
+            )))
+        }
+    };
+
+    match &change[1..] {
+        "+" => Ok(rights | right),
+        "-" => Ok(rights & !right),
+        _ => Err(chess_error(&format!(
+            "Invalid castling change \"{change}\""
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(builder: &mut BoardBuilder, commands: &[&str]) -> Result<()> {
+        for command in commands {
+            execute(builder, command)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn set_up_a_position() -> Result<()> {
+        let mut builder = BoardBuilder::new();
+        run(
+            &mut builder,
+            &[
+                "put wK e1",
+                "put wR h1",
+                "put wR a1",
+                "put bK e8",
+                "put bp d4",
+                "put wP e4",
+                "put wQ d1",
+                "remove d1",
+                "turn b",
+                "castling K+ Q+",
+                "castling Q-",
+                "ep e3",
+            ],
+        )?;
+        assert_eq!(
+            fen::export(&builder.build()?),
+            "4k3/8/8/8/3pP3/8/8/R3K2R b K e3 0 0"
+        );
+
+        run(&mut builder, &["castling -", "ep -", "turn w"])?;
+        assert_eq!(
+            fen::export(&builder.build()?),
+            "4k3/8/8/8/3pP3/8/8/R3K2R w - - 0 0"
+        );
+
+        execute(&mut builder, "clear")?;
+        assert!(builder.build().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_commands_are_rejected() -> Result<()> {
+        let mut builder = BoardBuilder::new();
+        run(&mut builder, &["put wK e1", "put bK e8"])?;
+
+        for command in [
+            "",
+            "put",
+            "put wK",
+            "put wX e4",
+            "put xK e4",
+            "put wKK e4",
+            "put wK i9",
+            "remove",
+            "turn x",
+            "castling",
+            "castling X+",
+            "castling K",
+            "castling K*",
+            "ep e9",
+            "jump e4",
+        ] {
+            assert!(execute(&mut builder, command).is_err(), "{command}");
+        }
+
+        // Nothing was changed by the invalid commands
+        assert_eq!(
+            fen::export(&builder.build()?),
+            fen::export(&fen::import("4k3/8/8/8/8/8/8/4K3 w - - 0 1")?)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+use crate::board::Board;
+use crate::geometry::chebyshev_distance;
+use crate::piece::{
+    opposite_color, piece_color, piece_type, Color, Piece, BITS_BISHOP, BITS_BLACK, BITS_KING,
+    BITS_PAWN, BITS_WHITE,
+};
+use crate::square::Square;
+
+/// Check if the pawn on `pawn_sq` is a passed pawn, i.e. no enemy pawn stands in front of it on
+/// its own file or on an adjacent file.
+pub fn is_passed_pawn(board: &Board, pawn_sq: &Square) -> bool {
+    let pawn = board.get_piece(pawn_sq);
+    assert_eq!(piece_type(pawn), BITS_PAWN);
+    let color = piece_color(pawn);
+
+    let ahead = |rank: usize| {
+        if color == BITS_WHITE {
+            rank > pawn_sq.1
+        } else {
+            rank < pawn_sq.1
+        }
+    };
+
+    let first_file = pawn_sq.0.saturating_sub(1);
+    let last_file = (pawn_sq.0 + 1).min(7);
+    !(first_file..=last_file).any(|file| {
+        (0..8).filter(|rank| ahead(*rank)).any(|rank| {
+            let p = board.get_piece(&Square(file, rank));
+            piece_type(p) == BITS_PAWN && piece_color(p) != color
+        })
+    })
+}
+
+/// Number of moves the pawn on `pawn_sq` needs to reach the last rank, taking the initial double
+/// step into account. Pieces in the way are not considered.
+pub fn promotion_distance(board: &Board, pawn_sq: &Square) -> usize {
+    let pawn = board.get_piece(pawn_sq);
+    assert_eq!(piece_type(pawn), BITS_PAWN);
+
+    let (dist, start_rank) = if piece_color(pawn) == BITS_WHITE {
+        (7 - pawn_sq.1, 1)
+    } else {
+        (pawn_sq.1, 6)
+    };
+
+    if pawn_sq.1 == start_rank {
+        dist - 1
+    } else {
+        dist
+    }
+}
+
+/// Check if the enemy king can catch the pawn on `pawn_sq` before it promotes, using the rule of
+/// the square. Only the two kings and the pawn are considered; other pieces that could block the
+/// king or the pawn are ignored.
+pub fn can_king_catch_pawn(board: &Board, pawn_sq: &Square) -> bool {
+    let color = piece_color(board.get_piece(pawn_sq));
+    let Some(king_sq) = board.king_square(opposite_color(color)) else {
+        return false;
+    };
+
+    let promotion_sq = Square(pawn_sq.0, if color == BITS_WHITE { 7 } else { 0 });
+    let king_dist = chebyshev_distance(&king_sq, &promotion_sq);
+    let tempo = usize::from(board.side_to_move() != color);
+
+    king_dist <= promotion_distance(board, pawn_sq) + tempo
+}
+
+/// Evaluate a race between passed pawns that the enemy king cannot catch. Returns the color and
+/// square of the pawn that promotes first, or None if no side has such a pawn.
+///
+/// When both sides have an unstoppable pawn, the side to move wins ties.
+pub fn pawn_race(board: &Board) -> Option<(Color, Square)> {
+    let fastest = |color: Color| {
+        pawn_squares(board, color)
+            .into_iter()
+            .filter(|sq| is_passed_pawn(board, sq) && !can_king_catch_pawn(board, sq))
+            .min_by_key(|sq| promotion_distance(board, sq))
+    };
+
+    let to_move = board.side_to_move();
+    match (fastest(to_move), fastest(opposite_color(to_move))) {
+        (None, None) => None,
+        (Some(sq), None) => Some((to_move, sq)),
+        (None, Some(sq)) => Some((opposite_color(to_move), sq)),
+        (Some(own), Some(other)) => {
+            if promotion_distance(board, &own) <= promotion_distance(board, &other) {
+                Some((to_move, own))
+            } else {
+                Some((opposite_color(to_move), other))
+            }
+        }
+    }
+}
+
+/// Scale factor meaning that an evaluation is kept as it is. See `DrawPattern`.
+pub const SCALE_NORMAL: u32 = 64;
+/// Scale factor meaning that the position is a dead draw. See `DrawPattern`.
+pub const SCALE_DRAW: u32 = 0;
+
+/// A drawish position pattern that material alone does not recognize, e.g. a fortress.
+///
+/// Implement this trait to add custom patterns to `eval::DrawPatternEvaluator`.
+pub trait DrawPattern {
+    /// If the pattern matches the position, return how much of the evaluation to keep, as a scale
+    /// factor between `SCALE_DRAW` and `SCALE_NORMAL`. Returns None if the pattern doesn't match.
+    fn scale_factor(&self, board: &Board) -> Option<u32>;
+}
+
+/// King, bishop and rook pawn(s) against a lone king, where the bishop cannot control the
+/// promotion square and the defending king has reached the corner.
+pub struct WrongColoredBishop;
+
+impl DrawPattern for WrongColoredBishop {
+    fn scale_factor(&self, board: &Board) -> Option<u32> {
+        for color in [BITS_WHITE, BITS_BLACK] {
+            let own = pieces_of(board, color);
+            let other = pieces_of(board, opposite_color(color));
+
+            let bishops = own
+                .iter()
+                .filter(|(_, p)| piece_type(*p) == BITS_BISHOP)
+                .collect::<Vec<_>>();
+            let pawns = own
+                .iter()
+                .filter(|(_, p)| piece_type(*p) == BITS_PAWN)
+                .collect::<Vec<_>>();
+            if other.len() != 1 || pawns.is_empty() || own.len() != 1 + bishops.len() + pawns.len()
+            {
+                continue;
+            }
+
+            // All pawns must be on the same rook file
+            let file = pawns[0].0 .0;
+            if (file != 0 && file != 7) || pawns.iter().any(|(sq, _)| sq.0 != file) {
+                continue;
+            }
+
+            let promotion_sq = Square(file, if color == BITS_WHITE { 7 } else { 0 });
+            let wrong_bishops = bishops
+                .iter()
+                .all(|(sq, _)| sq.is_dark() != promotion_sq.is_dark());
+            let defending_king = other[0].0;
+            if wrong_bishops && chebyshev_distance(&defending_king, &promotion_sq) <= 1 {
+                return Some(SCALE_DRAW);
+            }
+        }
+
+        None
+    }
+}
+
+/// Only kings and pawns are left and every pawn is blocked by an enemy pawn without any pawn
+/// captures available. Neither side can create a passed pawn, so the position is likely a draw
+/// unless a king can break in.
+pub struct BlockedPawnChain;
+
+impl DrawPattern for BlockedPawnChain {
+    fn scale_factor(&self, board: &Board) -> Option<u32> {
+        let pieces = pieces_of(board, BITS_WHITE)
+            .into_iter()
+            .chain(pieces_of(board, BITS_BLACK))
+            .filter(|(_, p)| piece_type(*p) != BITS_KING)
+            .collect::<Vec<_>>();
+        if pieces.is_empty() || pieces.iter().any(|(_, p)| piece_type(*p) != BITS_PAWN) {
+            return None;
+        }
+
+        let blocked = pieces.iter().all(|(sq, p)| {
+            let forward: i32 = if piece_color(*p) == BITS_WHITE { 1 } else { -1 };
+            let rank = sq.1 as i32 + forward;
+            let enemy_pawn_at = |file: i32| {
+                (0..8).contains(&file) && (0..8).contains(&rank) && {
+                    let other = board.get_piece(&Square(file as usize, rank as usize));
+                    piece_type(other) == BITS_PAWN && piece_color(other) != piece_color(*p)
+                }
+            };
+
+            let file = sq.0 as i32;
+            enemy_pawn_at(file) && !enemy_pawn_at(file - 1) && !enemy_pawn_at(file + 1)
+        });
+
+        if blocked {
+            Some(SCALE_NORMAL / 4)
+        } else {
+            None
+        }
+    }
+}
+
+/// The draw patterns recognized by default.
+pub fn default_draw_patterns() -> Vec<Box<dyn DrawPattern>> {
+    vec![Box::new(WrongColoredBishop), Box::new(BlockedPawnChain)]
+}
+
+fn pieces_of(board: &Board, color: Color) -> Vec<(Square, Piece)> {
+    board
+        .pieces_iter()
+        .filter(|(_, p)| piece_color(*p) == color)
+        .collect()
+}
+
+fn pawn_squares(board: &Board, color: Color) -> Vec<Square> {
+    board
+        .pieces_iter()
+        .filter(|(_, p)| piece_type(*p) == BITS_PAWN && piece_color(*p) == color)
+        .map(|(sq, _)| sq)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen;
+    use crate::square::square;
+
+    #[test]
+    fn passed_pawns() -> crate::Result<()> {
+        let board = fen::import("4k3/8/3p4/8/4P3/8/P6P/4K3 w - - 0 1")?;
+        assert!(!is_passed_pawn(&board, square!("e4")));
+        assert!(is_passed_pawn(&board, square!("a2")));
+        assert!(is_passed_pawn(&board, square!("h2")));
+        assert!(!is_passed_pawn(&board, square!("d6")));
+        Ok(())
+    }
+
+    #[test]
+    fn rule_of_the_square() -> crate::Result<()> {
+        // The black king is inside the square of the pawn only if black is to move
+        let board = fen::import("8/8/8/k7/8/8/6P1/K7 w - - 0 1")?;
+        assert!(!can_king_catch_pawn(&board, square!("g2")));
+        let board = fen::import("8/8/8/k7/8/8/6P1/K7 b - - 0 1")?;
+        assert!(can_king_catch_pawn(&board, square!("g2")));
+
+        let board = fen::import("8/8/8/8/8/5k2/1p6/4K3 w - - 0 1")?;
+        assert_eq!(promotion_distance(&board, square!("b2")), 1);
+        assert!(!can_king_catch_pawn(&board, square!("b2")));
+        Ok(())
+    }
+
+    #[test]
+    fn race() -> crate::Result<()> {
+        // Both pawns are unstoppable; white is one move ahead
+        let board = fen::import("7K/8/7p/8/P7/8/8/k7 b - - 0 1")?;
+        assert_eq!(pawn_race(&board), Some((BITS_WHITE, *square!("a4"))));
+        // Equal distance; the side to move wins
+        let board = fen::import("7K/8/7p/8/8/P7/8/k7 b - - 0 1")?;
+        assert_eq!(pawn_race(&board), Some((BITS_BLACK, *square!("h6"))));
+
+        let board = fen::import("8/8/8/8/8/8/8/K6k w - - 0 1")?;
+        assert_eq!(pawn_race(&board), None);
+        Ok(())
+    }
+
+    #[test]
+    fn wrong_colored_bishop() -> crate::Result<()> {
+        // The a8 corner is light, so a light-squared bishop can help the pawn through
+        let board = fen::import("k7/8/P7/8/8/8/6B1/4K3 w - - 0 1")?;
+        assert_eq!(WrongColoredBishop.scale_factor(&board), None);
+
+        let board = fen::import("k7/8/P7/8/8/8/7B/4K3 w - - 0 1")?;
+        assert_eq!(WrongColoredBishop.scale_factor(&board), Some(SCALE_DRAW));
+
+        // The defending king is too far away from the corner
+        let board = fen::import("8/8/P7/8/4k3/8/7B/4K3 w - - 0 1")?;
+        assert_eq!(WrongColoredBishop.scale_factor(&board), None);
+
+        // Black pawn promoting on h1, which is a light square, with a dark-squared bishop
+        let board = fen::import("4k3/8/1b6/8/8/7p/8/6K1 b - - 0 1")?;
+        assert_eq!(WrongColoredBishop.scale_factor(&board), Some(SCALE_DRAW));
+        Ok(())
+    }
+
+    #[test]
+    fn blocked_pawn_chain() -> crate::Result<()> {
+        let board = fen::import("4k3/8/8/1p1p1p2/1P1P1P2/8/8/4K3 w - - 0 1")?;
+        assert_eq!(
+            BlockedPawnChain.scale_factor(&board),
+            Some(SCALE_NORMAL / 4)
+        );
+
+        // e4xd5 is possible
+        let board = fen::import("4k3/8/8/3p4/3PP3/8/8/4K3 w - - 0 1")?;
+        assert_eq!(BlockedPawnChain.scale_factor(&board), None);
+        Ok(())
+    }
+}
@@ -15,3 +15,61 @@ impl Error for ChessError {}
 pub fn chess_error(msg: &str) -> Box<dyn Error> {
     Box::new(ChessError(String::from(msg)))
 }
+
+/// Error returned by `fen::import` when a field of a FEN string is missing or invalid. For
+/// invalid fields, the error that the field caused is available through `Error::source`.
+#[derive(Debug)]
+pub struct InvalidFen {
+    /// Name of the field, e.g. "halfmove clock"
+    pub field: &'static str,
+    /// The text of the field, or an empty string if the field is missing
+    pub text: String,
+    /// Byte offset of the field in the FEN string. For missing fields, this is the length of the
+    /// FEN string.
+    pub offset: usize,
+    source: Option<Box<dyn Error>>,
+}
+
+impl InvalidFen {
+    pub(crate) fn missing(field: &'static str, offset: usize) -> Self {
+        InvalidFen {
+            field,
+            text: String::new(),
+            offset,
+            source: None,
+        }
+    }
+
+    pub(crate) fn invalid(
+        field: &'static str,
+        text: &str,
+        offset: usize,
+        source: Box<dyn Error>,
+    ) -> Self {
+        InvalidFen {
+            field,
+            text: String::from(text),
+            offset,
+            source: Some(source),
+        }
+    }
+}
+
+impl fmt::Display for InvalidFen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match &self.source {
+            None => write!(f, "The {} field of the FEN string is missing", self.field),
+            Some(source) => write!(
+                f,
+                "Invalid {} field \"{}\" at byte {} of the FEN string: {}",
+                self.field, self.text, self.offset, source
+            ),
+        }
+    }
+}
+
+impl Error for InvalidFen {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.source.as_deref()
+    }
+}
@@ -0,0 +1,379 @@
+use crate::board::{Board, PHASE_VALUE_MAX};
+use crate::endgame::{self, DrawPattern, SCALE_NORMAL};
+use crate::piece::{
+    opposite_color, piece_color, piece_type, Piece, BITS_BISHOP, BITS_KING, BITS_KNIGHT, BITS_PAWN,
+    BITS_QUEEN, BITS_ROOK,
+};
+use crate::square::Square;
+
+/// Score of a position in centipawns.
+pub type Score = i32;
+
+/// Value of each piece type. Kings have no material value.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PieceValues {
+    pub pawn: Score,
+    pub knight: Score,
+    pub bishop: Score,
+    pub rook: Score,
+    pub queen: Score,
+}
+
+impl PieceValues {
+    /// The classic 1/3/3/5/9 values, in centipawns.
+    pub const CLASSIC: PieceValues = PieceValues {
+        pawn: 100,
+        knight: 300,
+        bishop: 300,
+        rook: 500,
+        queen: 900,
+    };
+
+    pub fn value(&self, piece: Piece) -> Score {
+        match piece_type(piece) {
+            BITS_PAWN => self.pawn,
+            BITS_KNIGHT => self.knight,
+            BITS_BISHOP => self.bishop,
+            BITS_ROOK => self.rook,
+            BITS_QUEEN => self.queen,
+            _ => 0,
+        }
+    }
+}
+
+impl Default for PieceValues {
+    fn default() -> Self {
+        Self::CLASSIC
+    }
+}
+
+/// Number of pieces of each type that one side has, as returned by `Board::material`. Kings are not
+/// counted.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct Material {
+    pub pawns: u32,
+    pub knights: u32,
+    pub bishops: u32,
+    pub rooks: u32,
+    pub queens: u32,
+}
+
+impl Material {
+    /// Total value of the pieces, e.g. 3900 for all pieces of the starting position with
+    /// `PieceValues::CLASSIC`.
+    pub fn value(&self, values: &PieceValues) -> Score {
+        self.pawns as Score * values.pawn
+            + self.knights as Score * values.knight
+            + self.bishops as Score * values.bishop
+            + self.rooks as Score * values.rook
+            + self.queens as Score * values.queen
+    }
+}
+
+/// Static evaluation of a position.
+pub trait Evaluator {
+    /// Evaluate `board` from the perspective of the side to move. Positive scores are good for
+    /// the side to move.
+    fn evaluate(&self, board: &Board) -> Score;
+}
+
+/// Evaluator that only counts material.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct MaterialEvaluator {
+    pub values: PieceValues,
+}
+
+impl MaterialEvaluator {
+    pub fn new(values: PieceValues) -> Self {
+        MaterialEvaluator { values }
+    }
+}
+
+impl Evaluator for MaterialEvaluator {
+    fn evaluate(&self, board: &Board) -> Score {
+        let side_to_move = board.side_to_move();
+        board.material(side_to_move).value(&self.values)
+            - board
+                .material(opposite_color(side_to_move))
+                .value(&self.values)
+    }
+}
+
+/// Interpolate between a midgame and an endgame score according to a phase value as returned by
+/// `Board::phase_value`. A phase value of `PHASE_VALUE_MAX` gives the midgame score and 0 gives
+/// the endgame score.
+pub fn taper(midgame: Score, endgame: Score, phase_value: u32) -> Score {
+    let phase = phase_value.min(PHASE_VALUE_MAX) as Score;
+    let max = PHASE_VALUE_MAX as Score;
+
+    (midgame * phase + endgame * (max - phase)) / max
+}
+
+/// The weights of one game phase used by `TaperedEvaluator`.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct PhaseWeights {
+    pub material: PieceValues,
+    /// Bonus for each step the king is away from the border of the board. Negative values keep the
+    /// king away from the center.
+    pub king_centralization: Score,
+}
+
+/// Evaluator with separate midgame and endgame weights that are interpolated by the game phase
+/// (see `taper`). This is the default evaluator of the crate.
+///
+/// In endings with only kings and pawns, the pawn that wins a race of unstoppable passed pawns
+/// (see `endgame::pawn_race`) is counted as a queen.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct TaperedEvaluator {
+    pub midgame: PhaseWeights,
+    pub endgame: PhaseWeights,
+}
+
+impl Default for TaperedEvaluator {
+    fn default() -> Self {
+        TaperedEvaluator {
+            midgame: PhaseWeights {
+                material: PieceValues {
+                    pawn: 82,
+                    knight: 337,
+                    bishop: 365,
+                    rook: 477,
+                    queen: 1025,
+                },
+                king_centralization: -10,
+            },
+            endgame: PhaseWeights {
+                material: PieceValues {
+                    pawn: 94,
+                    knight: 281,
+                    bishop: 297,
+                    rook: 512,
+                    queen: 936,
+                },
+                king_centralization: 10,
+            },
+        }
+    }
+}
+
+impl TaperedEvaluator {
+    fn evaluate_phase(weights: &PhaseWeights, board: &Board) -> Score {
+        (0..64)
+            .map(Square::from_index)
+            .map(|sq| {
+                let p = board.get_piece(&sq);
+                let mut value = weights.material.value(p);
+                if piece_type(p) == BITS_KING {
+                    // Number of steps to the closest border; 0 on the border and 3 in the center
+                    let centralization = sq.0.min(7 - sq.0).min(sq.1).min(7 - sq.1);
+                    value += weights.king_centralization * centralization as Score;
+                }
+
+                if piece_color(p) == board.side_to_move() {
+                    value
+                } else {
+                    -value
+                }
+            })
+            .sum()
+    }
+}
+
+impl Evaluator for TaperedEvaluator {
+    fn evaluate(&self, board: &Board) -> Score {
+        let midgame = Self::evaluate_phase(&self.midgame, board);
+        let endgame = Self::evaluate_phase(&self.endgame, board);
+
+        taper(midgame, endgame, board.phase_value())
+            + king_and_pawns_adjustment(board, &self.endgame.material)
+    }
+}
+
+/// Evaluator that scales the evaluation of another evaluator towards a draw when the position
+/// matches a known drawish pattern, such as a fortress. If several patterns match, the lowest
+/// scale factor is used.
+pub struct DrawPatternEvaluator<E: Evaluator> {
+    pub inner: E,
+    pub patterns: Vec<Box<dyn DrawPattern>>,
+}
+
+impl<E: Evaluator> DrawPatternEvaluator<E> {
+    /// Wrap `inner` using the default draw patterns.
+    pub fn new(inner: E) -> Self {
+        DrawPatternEvaluator {
+            inner,
+            patterns: endgame::default_draw_patterns(),
+        }
+    }
+
+    /// Add a custom pattern to the list of recognized patterns.
+    pub fn with_pattern(mut self, pattern: Box<dyn DrawPattern>) -> Self {
+        self.patterns.push(pattern);
+        self
+    }
+}
+
+impl<E: Evaluator> Evaluator for DrawPatternEvaluator<E> {
+    fn evaluate(&self, board: &Board) -> Score {
+        let score = self.inner.evaluate(board);
+        let scale = self
+            .patterns
+            .iter()
+            .filter_map(|pattern| pattern.scale_factor(board))
+            .min()
+            .unwrap_or(SCALE_NORMAL)
+            .min(SCALE_NORMAL);
+
+        score * scale as Score / SCALE_NORMAL as Score
+    }
+}
+
+/// In king and pawn endings, count the pawn that wins a pawn race as a queen. Returns 0 for all
+/// other positions. The score is seen from the side to move.
+pub fn king_and_pawns_adjustment(board: &Board, values: &PieceValues) -> Score {
+    // Only kings and pawns are left when there is no other material
+    if board.phase_value() != 0 {
+        return 0;
+    }
+
+    match endgame::pawn_race(board) {
+        Some((color, _)) if color == board.side_to_move() => values.queen - values.pawn,
+        Some(_) => values.pawn - values.queen,
+        None => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen;
+
+    #[test]
+    fn starting_position_is_balanced() {
+        assert_eq!(MaterialEvaluator::default().evaluate(&Board::new()), 0);
+    }
+
+    #[test]
+    fn material_is_scored_for_side_to_move() -> crate::Result<()> {
+        let evaluator = MaterialEvaluator::default();
+
+        let board = fen::import("4k3/8/8/8/8/8/P7/R3K3 w - - 0 1")?;
+        assert_eq!(evaluator.evaluate(&board), 600);
+
+        let board = fen::import("4k3/8/8/8/8/8/P7/R3K3 b - - 0 1")?;
+        assert_eq!(evaluator.evaluate(&board), -600);
+        Ok(())
+    }
+
+    #[test]
+    fn evaluation_is_color_symmetric() -> crate::Result<()> {
+        let evaluators: [Box<dyn Evaluator>; 3] = [
+            Box::new(MaterialEvaluator::default()),
+            Box::new(TaperedEvaluator::default()),
+            Box::new(DrawPatternEvaluator::new(TaperedEvaluator::default())),
+        ];
+
+        for fen in [
+            crate::positions::KIWIPETE,
+            "8/8/8/k7/8/8/6P1/K7 w - - 0 1",
+            "8/8/8/k7/8/8/6P1/K7 b - - 0 1",
+            "r3k3/1q6/8/8/3pP3/8/8/R3K2R b KQq e3 0 1",
+        ] {
+            let board = fen::import(fen)?;
+            for evaluator in &evaluators {
+                assert_eq!(
+                    evaluator.evaluate(&board),
+                    evaluator.evaluate(&board.flip_vertical()),
+                    "{fen}"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unstoppable_pawn_counts_as_queen() -> crate::Result<()> {
+        let evaluator = TaperedEvaluator::default();
+
+        // The black king is outside the square of the pawn
+        let board = fen::import("8/8/8/k7/8/8/6P1/K7 w - - 0 1")?;
+        assert_eq!(evaluator.evaluate(&board), 936);
+        // The material evaluator counts only the pawn
+        assert_eq!(MaterialEvaluator::default().evaluate(&board), 100);
+
+        // The black king catches the pawn
+        let board = fen::import("8/8/8/k7/8/8/6P1/K7 b - - 0 1")?;
+        assert_eq!(evaluator.evaluate(&board), -94);
+        Ok(())
+    }
+
+    #[test]
+    fn draw_patterns_scale_the_evaluation() -> crate::Result<()> {
+        let evaluator = DrawPatternEvaluator::new(MaterialEvaluator::default());
+
+        // Wrong-colored bishop
+        let board = fen::import("k7/8/P7/8/8/8/7B/4K3 w - - 0 1")?;
+        assert_eq!(evaluator.evaluate(&board), 0);
+
+        // Right-colored bishop
+        let board = fen::import("k7/8/P7/8/8/8/6B1/4K3 w - - 0 1")?;
+        assert_eq!(evaluator.evaluate(&board), 400);
+        Ok(())
+    }
+
+    #[test]
+    fn custom_draw_patterns() {
+        struct AlwaysHalf;
+        impl DrawPattern for AlwaysHalf {
+            fn scale_factor(&self, _: &Board) -> Option<u32> {
+                Some(SCALE_NORMAL / 2)
+            }
+        }
+
+        let board = crate::positions::kqk();
+        let plain = DrawPatternEvaluator::new(MaterialEvaluator::default());
+        let custom = DrawPatternEvaluator::new(MaterialEvaluator::default())
+            .with_pattern(Box::new(AlwaysHalf));
+        assert_eq!(plain.evaluate(&board), 900);
+        assert_eq!(custom.evaluate(&board), 450);
+    }
+
+    #[test]
+    fn taper_interpolates_by_phase() {
+        assert_eq!(taper(100, 200, PHASE_VALUE_MAX), 100);
+        assert_eq!(taper(100, 200, 0), 200);
+        assert_eq!(taper(100, 200, PHASE_VALUE_MAX / 2), 150);
+    }
+
+    #[test]
+    fn tapered_evaluation() -> crate::Result<()> {
+        let evaluator = TaperedEvaluator::default();
+        assert_eq!(evaluator.evaluate(&Board::new()), 0);
+
+        // A lone rook barely contributes to the phase, so the endgame values dominate
+        let board = fen::import("k7/8/8/8/8/8/8/R6K w - - 0 1")?;
+        assert_eq!(board.phase_value(), 2);
+        assert_eq!(evaluator.evaluate(&board), taper(477, 512, 2));
+
+        // A central king is good in the endgame
+        let board = fen::import("k7/8/8/8/3K4/8/8/R7 w - - 0 1")?;
+        assert_eq!(evaluator.evaluate(&board), taper(477 - 30, 512 + 30, 2));
+
+        // ... but bad in the middlegame
+        let central = fen::import("rnbqkbnr/pppppppp/8/8/3K4/8/PPPPPPPP/RNBQ1BNR w - - 0 1")?;
+        assert!(evaluator.evaluate(&central) < evaluator.evaluate(&Board::new()));
+        Ok(())
+    }
+
+    #[test]
+    fn piece_values_are_configurable() -> crate::Result<()> {
+        let evaluator = MaterialEvaluator::new(PieceValues {
+            bishop: 325,
+            ..PieceValues::CLASSIC
+        });
+
+        let board = fen::import("4k3/8/8/8/8/8/8/2BNK3 w - - 0 1")?;
+        assert_eq!(evaluator.evaluate(&board), 625);
+        Ok(())
+    }
+}
@@ -1,62 +1,111 @@
-use crate::board::Board;
-use crate::error::chess_error;
+use itertools::Itertools;
+
+use crate::bitboard;
+use crate::board::{
+    Board, CastlingRights, CASTLE_BLACK_KINGSIDE, CASTLE_BLACK_QUEENSIDE, CASTLE_NONE,
+    CASTLE_WHITE_KINGSIDE, CASTLE_WHITE_QUEENSIDE,
+};
+use crate::error::{chess_error, InvalidFen};
 use crate::piece::{
-    piece_color, piece_type, Color, Piece, BITS_BISHOP, BITS_BLACK, BITS_KING, BITS_KNIGHT,
-    BITS_NO_PIECE, BITS_PAWN, BITS_QUEEN, BITS_ROOK, BITS_WHITE,
+    piece_color, piece_type, Color, Piece, BITS_BISHOP, BITS_BLACK, BITS_HAS_MOVED, BITS_KING,
+    BITS_KNIGHT, BITS_NO_PIECE, BITS_PAWN, BITS_QUEEN, BITS_ROOK, BITS_WHITE,
 };
 use crate::square::Square;
 use crate::Result;
 
 use std::iter::zip;
 
+/// Read a position from a FEN string. A missing or invalid field is reported as an
+/// `InvalidFen` error.
 pub fn import(fen_pos: &str) -> Result<Board> {
-    let mut split = fen_pos.split(' ');
+    let mut fields = fen_pos.split(' ').scan(0, |offset, text| {
+        let field_offset = *offset;
+        *offset += text.len() + 1;
+        Some((field_offset, text))
+    });
+    let mut parse_field = |name: &'static str| {
+        fields
+            .next()
+            .ok_or_else(|| InvalidFen::missing(name, fen_pos.len()))
+    };
 
-    let piece_placement = split
-        .next()
-        .ok_or(chess_error("Piece placement field is missing"))?;
-    let piece_placement = import_piece_placement(piece_placement)?;
+    let (offset, text) = parse_field("piece placement")?;
+    let mut piece_placement = import_piece_placement(text)
+        .map_err(|e| InvalidFen::invalid("piece placement", text, offset, e))?;
 
-    let side_to_move = split
-        .next()
-        .ok_or(chess_error("Side-to-move field is missing"))?;
-    let side_to_move = import_side_to_move(side_to_move)?;
+    let (offset, text) = parse_field("side to move")?;
+    let side_to_move = import_side_to_move(text)
+        .map_err(|e| InvalidFen::invalid("side to move", text, offset, e))?;
 
-    let _castling_ability = split
-        .next()
-        .ok_or(chess_error("Castling ability field is missing"))?;
-    // TODO: Parse
+    let (offset, text) = parse_field("castling ability")?;
+    let castling_ability = import_castling_ability(text)
+        .map_err(|e| InvalidFen::invalid("castling ability", text, offset, e))?;
+    mark_moved_pieces(castling_ability, &mut piece_placement);
 
-    let en_passant_sq = split
-        .next()
-        .ok_or(chess_error("En passant target square field is missing"))?;
-    let en_passant_sq = if en_passant_sq != "-" {
-        Some(Square::from(en_passant_sq)?)
+    let (offset, text) = parse_field("en passant target square")?;
+    let en_passant_sq = if text != "-" {
+        Some(
+            Square::from(text)
+                .map_err(|e| InvalidFen::invalid("en passant target square", text, offset, e))?,
+        )
     } else {
         None
     };
 
-    // TODO: Parse
+    let (offset, text) = parse_field("halfmove clock")?;
+    let halfmove_clock = text
+        .parse::<u32>()
+        .map_err(|e| InvalidFen::invalid("halfmove clock", text, offset, Box::new(e)))?;
 
-    let _halfmove_clock = split
-        .next()
-        .ok_or(chess_error("Halfmove clock field is missing"))?;
+    let _fullmove_counter = parse_field("fullmove counter")?;
     // TODO: Parse
 
-    let _fullmove_counter = split
-        .next()
-        .ok_or(chess_error("Halfmove counter field is missing"))?;
-    // TODO: Parse
-
-    Ok(Board {
+    let mut board = Board {
         pieces: piece_placement,
         side_to_move,
         en_passant: en_passant_sq,
-    })
+        halfmove_clock,
+        history: Vec::new(),
+        position_hashes: Vec::new(),
+        king_squares: [None; 2],
+        occupancy: [bitboard::EMPTY; 2],
+        piece_hash: 0,
+        piece_attacks: [bitboard::EMPTY; 64],
+    };
+    board.track_pieces();
+
+    Ok(board)
 }
 
-fn import_piece_placement(placement: &str) -> Result<Box<[[Piece; 8]; 8]>> {
-    let mut res = Box::new([[BITS_NO_PIECE; 8]; 8]);
+/// Like `import`, but also reject positions that could not occur in a game (see `Board::validate`)
+/// and castling ability fields that grant rights the kings and rooks can't have, e.g. `K` without
+/// a rook on h1. `import` silently drops such rights.
+pub fn import_validated(fen_pos: &str) -> Result<Board> {
+    let board = import(fen_pos)?;
+
+    // The field is known to be present and valid after a successful import
+    let requested = import_castling_ability(fen_pos.split(' ').nth(2).unwrap())?;
+    check_castling_ability(requested, &board)?;
+    board.validate()?;
+
+    Ok(board)
+}
+
+/// Return an error if `board` lacks any of the castling rights in `requested`.
+pub(crate) fn check_castling_ability(requested: CastlingRights, board: &Board) -> Result<()> {
+    let impossible = requested & !board.castling_rights();
+    if impossible != CASTLE_NONE {
+        return Err(chess_error(&format!(
+            "Castling ability \"{}\" is not possible with the kings and rooks of the position",
+            export_castling_ability(impossible)
+        )));
+    }
+
+    Ok(())
+}
+
+fn import_piece_placement(placement: &str) -> Result<[[Piece; 8]; 8]> {
+    let mut res = [[BITS_NO_PIECE; 8]; 8];
 
     let ranks = placement.split('/');
 
@@ -67,7 +116,7 @@ fn import_piece_placement(placement: &str) -> Result<Box<[[Piece; 8]; 8]>> {
     Ok(res)
 }
 
-fn import_rank(rank_idx: usize, rank: &str, pieces: &mut Box<[[Piece; 8]; 8]>) -> Result<()> {
+fn import_rank(rank_idx: usize, rank: &str, pieces: &mut [[Piece; 8]; 8]) -> Result<()> {
     let mut next_piece_file = 0;
 
     for ch in rank.chars() {
@@ -93,7 +142,7 @@ fn import_rank(rank_idx: usize, rank: &str, pieces: &mut Box<[[Piece; 8]; 8]>) -
     Ok(())
 }
 
-fn import_piece(letter: char) -> Result<Piece> {
+pub(crate) fn import_piece(letter: char) -> Result<Piece> {
     let piece_type = match letter.to_uppercase().next().unwrap() {
         'B' => BITS_BISHOP,
         'K' => BITS_KING,
@@ -113,6 +162,54 @@ fn import_piece(letter: char) -> Result<Piece> {
     Ok(color | piece_type)
 }
 
+fn import_castling_ability(castling_ability: &str) -> Result<CastlingRights> {
+    if castling_ability == "-" {
+        return Ok(CASTLE_NONE);
+    }
+
+    let mut rights = CASTLE_NONE;
+    for ch in castling_ability.chars() {
+        rights |= match ch {
+            'K' => CASTLE_WHITE_KINGSIDE,
+            'Q' => CASTLE_WHITE_QUEENSIDE,
+            'k' => CASTLE_BLACK_KINGSIDE,
+            'q' => CASTLE_BLACK_QUEENSIDE,
+            _ => {
+                return Err(chess_error(&format!(
+                    "Invalid castling ability field \"{}\"",
+                    castling_ability
+                )))
+            }
+        };
+    }
+
+    Ok(rights)
+}
+
+/// Set the has-moved bit on every king and rook that may not castle according to `rights`.
+///
+/// FEN does not store whether a piece has moved, so a king or rook is only considered unmoved
+/// if it stands on its home square and the castling ability field grants a right that needs it.
+pub(crate) fn mark_moved_pieces(rights: CastlingRights, pieces: &mut [[Piece; 8]; 8]) {
+    for (file, rank) in (0..8).cartesian_product(0..8) {
+        let piece = pieces[file][rank];
+        let needed_for = match (piece_type(piece), piece_color(piece), file, rank) {
+            (BITS_KING, BITS_WHITE, 4, 0) => CASTLE_WHITE_KINGSIDE | CASTLE_WHITE_QUEENSIDE,
+            (BITS_KING, BITS_BLACK, 4, 7) => CASTLE_BLACK_KINGSIDE | CASTLE_BLACK_QUEENSIDE,
+            (BITS_ROOK, BITS_WHITE, 7, 0) => CASTLE_WHITE_KINGSIDE,
+            (BITS_ROOK, BITS_WHITE, 0, 0) => CASTLE_WHITE_QUEENSIDE,
+            (BITS_ROOK, BITS_BLACK, 7, 7) => CASTLE_BLACK_KINGSIDE,
+            (BITS_ROOK, BITS_BLACK, 0, 7) => CASTLE_BLACK_QUEENSIDE,
+            (BITS_KING, _, _, _) | (BITS_ROOK, _, _, _) => CASTLE_NONE,
+            _ => continue,
+        };
+
+        if rights & needed_for == CASTLE_NONE {
+            pieces[file][rank] |= BITS_HAS_MOVED;
+        }
+    }
+}
+
 fn import_side_to_move(side_to_move: &str) -> Result<Color> {
     if side_to_move.len() != 1 {
         return Err(chess_error(&format!(
@@ -121,7 +218,7 @@ fn import_side_to_move(side_to_move: &str) -> Result<Color> {
         )));
     }
 
-    let color = side_to_move.chars().nth(0).unwrap();
+    let color = side_to_move.chars().next().unwrap();
     match color {
         'w' => Ok(BITS_WHITE),
         'b' => Ok(BITS_BLACK),
@@ -138,7 +235,8 @@ pub fn export(board: &Board) -> String {
     for rank in (0..8).rev() {
         let mut steps_to_next_piece = 0;
         for file in 0..8 {
-            match piece_type(board.get_piece(&Square(file, rank))) {
+            let piece = board.get_piece(&Square(file, rank));
+            match piece_type(piece) {
                 BITS_NO_PIECE => {
                     steps_to_next_piece += 1;
                     if file == 7 {
@@ -146,13 +244,13 @@ pub fn export(board: &Board) -> String {
                         res.push_str(steps_to_next_piece.to_string().as_str());
                     }
                 }
-                p_type => {
+                _ => {
                     if steps_to_next_piece > 0 {
                         res.push_str(steps_to_next_piece.to_string().as_str());
                     }
                     steps_to_next_piece = 0;
 
-                    res.push(piece_to_letter(p_type));
+                    res.push(piece_to_letter(piece));
                 }
             }
         }
@@ -168,15 +266,15 @@ pub fn export(board: &Board) -> String {
         _ => panic!("Invalid color"),
     }
 
-    // TODO: Castling
-    res.push_str(" KQkq");
+    res.push(' ');
+    res.push_str(&export_castling_ability(board.castling_rights()));
 
-    // TODO: En passant
-    let en_passant_sq = board.en_passant.map_or(String::from("-"), |sq| sq.to_str());
+    let en_passant_sq = board
+        .en_passant
+        .map_or(String::from("-"), |sq| sq.to_str().to_lowercase());
     res.push_str(&format!(" {en_passant_sq}"));
 
-    // TODO: Halfmove clock
-    res.push_str(" 0");
+    res.push_str(&format!(" {}", board.halfmove_clock()));
 
     // TODO: Fullmove counter
     res.push_str(" 0");
@@ -184,6 +282,146 @@ pub fn export(board: &Board) -> String {
     res
 }
 
+fn export_castling_ability(rights: CastlingRights) -> String {
+    if rights == CASTLE_NONE {
+        return String::from("-");
+    }
+
+    [
+        (CASTLE_WHITE_KINGSIDE, 'K'),
+        (CASTLE_WHITE_QUEENSIDE, 'Q'),
+        (CASTLE_BLACK_KINGSIDE, 'k'),
+        (CASTLE_BLACK_QUEENSIDE, 'q'),
+    ]
+    .iter()
+    .filter(|(right, _)| rights & right != CASTLE_NONE)
+    .map(|(_, letter)| letter)
+    .collect()
+}
+
+/// Bit field type that selects which of the six space-separated fields of FEN strings to compare
+/// with `compare_fen`.
+pub type FenComparator = u32;
+
+/// Compare the position of the pieces
+pub const CMP_POS: FenComparator = 1 << 5;
+/// Compare the side to move
+pub const CMP_SIDE_TO_MOVE: FenComparator = 1 << 4;
+/// Compare possibilities to castle
+pub const CMP_CASTLING: FenComparator = 1 << 3;
+/// Compare en passant
+pub const CMP_EN_PASSANT: FenComparator = 1 << 2;
+/// Compare halfmove clock (50 move rule)
+pub const CMP_HALFMOVE_CLOCK: FenComparator = 1 << 1;
+/// Compare fullmove counter (Incremented after each Black's move)
+pub const CMP_FULLMOVE_COUNTER: FenComparator = 1 << 0;
+/// Compare all fields
+pub const CMP_ALL: FenComparator = CMP_POS
+    | CMP_SIDE_TO_MOVE
+    | CMP_CASTLING
+    | CMP_EN_PASSANT
+    | CMP_HALFMOVE_CLOCK
+    | CMP_FULLMOVE_COUNTER;
+
+/// Compare the fields of two FEN strings selected by `comparator`. The fields are compared as
+/// text, so e.g. the same castling rights given in a different order are considered different.
+///
+/// Returns an error if either string doesn't consist of exactly six fields.
+pub fn compare_fen(fen_lhs: &str, fen_rhs: &str, comparator: FenComparator) -> Result<bool> {
+    let fields_lhs = fen_lhs.split(' ').collect_vec();
+    let fields_rhs = fen_rhs.split(' ').collect_vec();
+    if fields_lhs.len() != 6 || fields_rhs.len() != 6 {
+        return Err(chess_error(&format!(
+            "Comparing invalid FEN formats ({} and {})",
+            fen_lhs, fen_rhs
+        )));
+    }
+
+    // The first field is selected by the most significant bit
+    Ok(zip(fields_lhs, fields_rhs)
+        .enumerate()
+        .all(|(i, (lhs, rhs))| comparator & (1 << (5 - i)) == 0 || lhs == rhs))
+}
+
+/// Differences between two positions given in FEN, as returned by `diff`. Each field that is
+/// an Option is None if the positions agree on it, and holds the values of the first and the
+/// second position otherwise.
+///
+/// Pieces are given as color and piece type only; whether kings and rooks have moved is
+/// reflected by the castling rights.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FenDiff {
+    /// Pieces that left one square and appeared on another, as (from, to, piece)
+    pub moved: Vec<(Square, Square, Piece)>,
+    /// Pieces only in the second position that didn't move there from another square
+    pub added: Vec<(Square, Piece)>,
+    /// Pieces only in the first position that didn't move to another square
+    pub removed: Vec<(Square, Piece)>,
+    pub side_to_move: Option<(Color, Color)>,
+    pub castling: Option<(CastlingRights, CastlingRights)>,
+    pub en_passant: Option<(Option<Square>, Option<Square>)>,
+    pub halfmove_clock: Option<(u32, u32)>,
+}
+
+impl FenDiff {
+    /// Check if the positions are the same.
+    pub fn is_empty(&self) -> bool {
+        *self == FenDiff::default()
+    }
+}
+
+/// Compute what changed between the positions `fen_a` and `fen_b`.
+///
+/// A piece that disappears from one square and appears on another is reported as moved, so a
+/// capture shows up as a moved piece and a removed piece. If several pieces of the same kind
+/// disappear and appear, they are paired up in square order.
+pub fn diff(fen_a: &str, fen_b: &str) -> Result<FenDiff> {
+    let a = import(fen_a)?;
+    let b = import(fen_b)?;
+    let strip = |p: Piece| piece_color(p) | piece_type(p);
+
+    let mut res = FenDiff::default();
+    for sq in (0..64).map(Square::from_index) {
+        let (p_a, p_b) = (strip(a.get_piece(&sq)), strip(b.get_piece(&sq)));
+        if p_a == p_b {
+            continue;
+        }
+        if piece_type(p_a) != BITS_NO_PIECE {
+            res.removed.push((sq, p_a));
+        }
+        if piece_type(p_b) != BITS_NO_PIECE {
+            res.added.push((sq, p_b));
+        }
+    }
+
+    let mut removed = Vec::new();
+    for (from, piece) in res.removed {
+        match res.added.iter().position(|(_, p)| *p == piece) {
+            Some(i) => {
+                let (to, _) = res.added.remove(i);
+                res.moved.push((from, to, piece));
+            }
+            None => removed.push((from, piece)),
+        }
+    }
+    res.removed = removed;
+
+    res.side_to_move = changed(a.side_to_move(), b.side_to_move());
+    res.castling = changed(a.castling_rights(), b.castling_rights());
+    res.en_passant = changed(a.en_passant, b.en_passant);
+    res.halfmove_clock = changed(a.halfmove_clock(), b.halfmove_clock());
+
+    Ok(res)
+}
+
+fn changed<T: PartialEq>(lhs: T, rhs: T) -> Option<(T, T)> {
+    if lhs != rhs {
+        Some((lhs, rhs))
+    } else {
+        None
+    }
+}
+
 pub fn piece_to_letter(piece_bits: Piece) -> char {
     let ch = match piece_type(piece_bits) {
         BITS_BISHOP => 'b',
@@ -205,9 +443,11 @@ pub fn piece_to_letter(piece_bits: Piece) -> char {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::error::Error;
 
     use crate::fen;
-    use crate::internal::test_utils::fen::{compare_fen, CMP_POS, CMP_SIDE_TO_MOVE};
+    use crate::piece::has_moved;
+    use crate::square::square;
 
     #[test]
     fn export_is_the_inverse_of_import() {
@@ -228,10 +468,206 @@ mod tests {
             .collect();
 
         assert!(zip(arbitrary_fens, res).all(|(a, b)| compare_fen(
-            &a,
+            a,
             &b,
-            CMP_POS & CMP_SIDE_TO_MOVE
+            CMP_POS | CMP_SIDE_TO_MOVE
         )
         .unwrap_or(false)));
     }
+
+    #[test]
+    fn export_keeps_piece_colors() {
+        let board = fen::import("4k3/8/8/8/8/8/8/4K2r w - - 0 1").unwrap();
+        assert!(fen::export(&board).starts_with("4k3/8/8/8/8/8/8/4K2r w"));
+    }
+
+    #[test]
+    fn castling_ability_is_kept_through_import_and_export() {
+        let fens = vec![
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R b Q - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1",
+        ];
+
+        for fen in fens {
+            let board = fen::import(fen).unwrap();
+            assert!(compare_fen(fen, &fen::export(&board), CMP_CASTLING).unwrap());
+        }
+    }
+
+    #[test]
+    fn castling_ability_marks_pieces_without_rights_as_moved() {
+        let board = fen::import("r3k2r/8/8/8/8/8/8/R3K2R w K - 0 1").unwrap();
+
+        assert!(!has_moved(board.get_piece(square!("e1"))));
+        assert!(!has_moved(board.get_piece(square!("h1"))));
+        assert!(has_moved(board.get_piece(square!("a1"))));
+        assert!(has_moved(board.get_piece(square!("e8"))));
+        assert!(has_moved(board.get_piece(square!("a8"))));
+        assert!(has_moved(board.get_piece(square!("h8"))));
+    }
+
+    #[test]
+    fn halfmove_clock_is_kept_through_import_and_export() {
+        let fen = "8/8/8/4k3/8/8/8/R3K3 w - - 37 0";
+        let board = fen::import(fen).unwrap();
+
+        assert_eq!(board.halfmove_clock(), 37);
+        assert!(compare_fen(fen, &fen::export(&board), CMP_HALFMOVE_CLOCK).unwrap());
+        assert!(fen::import("8/8/8/4k3/8/8/8/R3K3 w - - x 0").is_err());
+    }
+
+    #[test]
+    fn diff() -> Result<()> {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert!(fen::diff(start, start)?.is_empty());
+
+        let after_e4 = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+        assert_eq!(
+            fen::diff(start, after_e4)?,
+            FenDiff {
+                moved: vec![(*square!("e2"), *square!("e4"), BITS_WHITE | BITS_PAWN)],
+                side_to_move: Some((BITS_WHITE, BITS_BLACK)),
+                en_passant: Some((None, Some(*square!("e3")))),
+                ..FenDiff::default()
+            }
+        );
+
+        // A capture, losing the castling rights of the captured rook
+        let diff = fen::diff(
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 7 1",
+            "R3k2r/8/8/8/8/8/8/4K2R b Kk - 0 1",
+        )?;
+        assert_eq!(
+            diff.moved,
+            vec![(*square!("a1"), *square!("a8"), BITS_WHITE | BITS_ROOK)]
+        );
+        assert_eq!(diff.removed, vec![(*square!("a8"), BITS_BLACK | BITS_ROOK)]);
+        assert!(diff.added.is_empty());
+        assert_eq!(
+            diff.castling,
+            Some((
+                CASTLE_WHITE_KINGSIDE
+                    | CASTLE_WHITE_QUEENSIDE
+                    | CASTLE_BLACK_KINGSIDE
+                    | CASTLE_BLACK_QUEENSIDE,
+                CASTLE_WHITE_KINGSIDE | CASTLE_BLACK_KINGSIDE
+            ))
+        );
+        assert_eq!(diff.halfmove_clock, Some((7, 0)));
+
+        // A promotion removes the pawn and adds the new piece
+        let diff = fen::diff(
+            "8/4P3/8/8/8/8/8/k6K w - - 0 1",
+            "4Q3/8/8/8/8/8/8/k6K b - - 0 1",
+        )?;
+        assert_eq!(diff.removed, vec![(*square!("e7"), BITS_WHITE | BITS_PAWN)]);
+        assert_eq!(diff.added, vec![(*square!("e8"), BITS_WHITE | BITS_QUEEN)]);
+        assert!(diff.moved.is_empty());
+
+        assert!(fen::diff(start, "invalid").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn compare_fen_selects_fields() {
+        let lhs = "8/8/8/4k3/8/8/8/R3K3 w - - 12 40";
+        let rhs = "8/8/8/4k3/8/8/8/R3K3 b - - 12 41";
+
+        assert!(compare_fen(lhs, lhs, CMP_ALL).unwrap());
+        assert!(!compare_fen(lhs, rhs, CMP_ALL).unwrap());
+        assert!(compare_fen(lhs, rhs, CMP_POS | CMP_CASTLING | CMP_HALFMOVE_CLOCK).unwrap());
+        assert!(!compare_fen(lhs, rhs, CMP_SIDE_TO_MOVE).unwrap());
+        assert!(!compare_fen(lhs, rhs, CMP_FULLMOVE_COUNTER).unwrap());
+
+        assert!(compare_fen(lhs, "8/8/8/4k3/8/8/8/R3K3 w - -", CMP_POS).is_err());
+        assert!(compare_fen(lhs, "8/8/8/4k3/8/8/8/R3K3 w - - 12 40 x", CMP_POS).is_err());
+    }
+
+    #[test]
+    fn invalid_castling_ability_is_rejected() {
+        assert!(fen::import("r3k2r/8/8/8/8/8/8/R3K2R w KX - 0 1").is_err());
+    }
+
+    #[test]
+    fn import_errors_point_out_the_field() {
+        let error = |fen: &str| {
+            let err = fen::import(fen).err().unwrap();
+            let err = err.downcast::<InvalidFen>().unwrap();
+            (
+                err.field,
+                err.text.clone(),
+                err.offset,
+                err.source().is_some(),
+            )
+        };
+
+        assert_eq!(
+            error("r3k2r/8/8/8/8/8/8/R3K2R w KX - 0 1"),
+            ("castling ability", String::from("KX"), 26, true)
+        );
+        assert_eq!(
+            error("8/8/8/4k3/8/8/8/4K3 w - - x 1"),
+            ("halfmove clock", String::from("x"), 26, true)
+        );
+        assert_eq!(
+            error("8/8/8/4k3/8/8/8/4K3 w - e9 0 1"),
+            ("en passant target square", String::from("e9"), 24, true)
+        );
+        assert_eq!(
+            error("8/8/8/4k3/8/8/8/4K3 w -"),
+            ("en passant target square", String::new(), 23, false)
+        );
+
+        let err = fen::import("8/8/8/4k3/8/8/8/4X3 w - - 0 1").err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Invalid piece placement field \"8/8/8/4k3/8/8/8/4X3\" at byte 0 of the FEN string: \
+             Invalid piece type 'X'"
+        );
+        assert_eq!(err.source().unwrap().to_string(), "Invalid piece type 'X'");
+    }
+
+    #[test]
+    fn import_validated() -> Result<()> {
+        let error = |fen: &str| fen::import_validated(fen).unwrap_err().to_string();
+
+        fen::import_validated(crate::positions::KIWIPETE)?;
+        fen::import_validated("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1")?;
+
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/4K2R w Kq - 0 1"),
+            "Castling ability \"q\" is not possible with the kings and rooks of the position"
+        );
+        // Accepted without validation, with the impossible right dropped
+        assert_eq!(
+            export(&fen::import("4k3/8/8/8/8/8/8/4K2R w Kq - 0 1")?),
+            "4k3/8/8/8/8/8/8/4K2R w K - 0 0"
+        );
+
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/8 w - - 0 1"),
+            "White has 0 kings, expected 1"
+        );
+        assert_eq!(
+            error("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+            "White has 2 kings, expected 1"
+        );
+        assert_eq!(error("4k3/8/8/8/8/8/8/4K2p w - - 0 1"), "Pawn on h1");
+        assert_eq!(
+            error("4k3/8/8/8/8/8/4R3/4K3 w - - 0 1"),
+            "The side that is not to move is in check"
+        );
+        // Wrong rank, no pawn that could have made a double step, and the square behind the
+        // pawn is occupied
+        for fen in [
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d3 0 1",
+            "4k3/8/8/4P3/8/8/8/4K3 w - d6 0 1",
+            "4k3/3n4/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ] {
+            assert!(error(fen).starts_with("Invalid en passant square"), "{fen}");
+        }
+        Ok(())
+    }
 }
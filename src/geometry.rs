@@ -0,0 +1,52 @@
+use crate::square::Square;
+
+/// Largest possible Manhattan distance between two squares (A1 to H8).
+pub const MAX_MANHATTAN_DISTANCE: usize = 14;
+
+pub fn file_distance(a: &Square, b: &Square) -> usize {
+    a.0.abs_diff(b.0)
+}
+
+pub fn rank_distance(a: &Square, b: &Square) -> usize {
+    a.1.abs_diff(b.1)
+}
+
+/// Number of king moves needed to walk from one square to the other on an empty board.
+pub fn chebyshev_distance(a: &Square, b: &Square) -> usize {
+    file_distance(a, b).max(rank_distance(a, b))
+}
+
+/// Number of rook steps of length one needed to walk from one square to the other.
+pub fn manhattan_distance(a: &Square, b: &Square) -> usize {
+    file_distance(a, b) + rank_distance(a, b)
+}
+
+/// Closeness weight between two squares, e.g. between an attacking piece and the enemy king.
+///
+/// The weight is `MAX_MANHATTAN_DISTANCE` minus the Manhattan distance, so it ranges from 0 for
+/// opposite corners to 14 for the same square.
+pub fn tropism(a: &Square, b: &Square) -> i32 {
+    (MAX_MANHATTAN_DISTANCE - manhattan_distance(a, b)) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::square;
+
+    #[test]
+    fn distances() {
+        assert_eq!(chebyshev_distance(square!("a1"), square!("h8")), 7);
+        assert_eq!(manhattan_distance(square!("a1"), square!("h8")), 14);
+        assert_eq!(chebyshev_distance(square!("e4"), square!("f6")), 2);
+        assert_eq!(manhattan_distance(square!("e4"), square!("f6")), 3);
+        assert_eq!(chebyshev_distance(square!("e4"), square!("e4")), 0);
+    }
+
+    #[test]
+    fn tropism_grows_when_closer() {
+        assert_eq!(tropism(square!("a1"), square!("h8")), 0);
+        assert_eq!(tropism(square!("d4"), square!("d4")), 14);
+        assert!(tropism(square!("g1"), square!("f3")) > tropism(square!("g1"), square!("c6")));
+    }
+}
@@ -1,156 +1,69 @@
 #[cfg(test)]
-pub mod fen {
-    use itertools::izip;
-
-    use crate::{error::chess_error, Result};
-
-    /// Bitfield-based type that specifies which space-separated fields of FEN formats to compare
-    pub type FenComparator = u32;
-    /// Compare the position of the pieces
-    pub static CMP_POS: FenComparator = 1 << 5;
-    /// Compare the side to move
-    pub static CMP_SIDE_TO_MOVE: FenComparator = 1 << 4;
-    /// Compare possibilities to castle
-    pub static CMP_CASTLING: FenComparator = 1 << 3;
-    /// Compare en passant
-    pub static CMP_EN_PASSANT: FenComparator = 1 << 2;
-    /// Compare halfmove clock (50 move rule)
-    pub static CMP_HALFMOVE_CLOCK: FenComparator = 1 << 1;
-    /// Compare fullmove counter (Incremented after each Black's move)
-    pub static CMP_FULLMOVE_COUNTER: FenComparator = 1 << 0;
-    /// Compare all fields
-    pub static _CMP_ALL: FenComparator = CMP_POS
-        | CMP_SIDE_TO_MOVE
-        | CMP_CASTLING
-        | CMP_EN_PASSANT
-        | CMP_HALFMOVE_CLOCK
-        | CMP_FULLMOVE_COUNTER;
-
-    pub fn compare_fen(fen_lhs: &str, fen_rhs: &str, comparator: FenComparator) -> Result<bool> {
-        for (i, p1, p2) in izip!(0.., fen_lhs.split(" "), fen_rhs.split(" ")) {
-            if i >= 6 {
-                return Err(chess_error(&format!(
-                    "Comparing invalid FEN formats ({} and {})",
-                    p1, p2
-                )));
-            }
+pub mod json {
+    use crate::conformance::{self, Failure};
+    use crate::fen::FenComparator;
+    use crate::Result;
 
-            // Check if this space-separated specified should be compared
-            if comparator & (1 << i) != 0 && p1 != p2 {
-                return Ok(false);
-            }
+    fn assert_no_failures(failures: Vec<Failure>) {
+        for failure in &failures {
+            eprintln!("{} failed: {}", failure.test_case, failure.message);
         }
-
-        Ok(true)
+        assert!(failures.is_empty(), "{} checks failed", failures.len());
     }
-}
-
-#[cfg(test)]
-pub mod json {
-    use serde::Deserialize;
-
-    use crate::board::Board;
-    use crate::error::chess_error;
-    use crate::square::Square;
-    use crate::{fen, Result};
-
-    use std::fs::File;
-    use std::io::{BufReader, Read};
-
-    use super::fen::compare_fen;
 
-    #[derive(Deserialize)]
-    struct TestCases {
-        description: Option<String>,
-        testcases: Vec<TestCase>,
-    }
+    pub fn run_check_num_moves_test(json_file_path: &str) -> Result<()> {
+        let suite = conformance::load(json_file_path)?;
+        assert_no_failures(conformance::check_moves(&suite)?);
 
-    #[derive(Deserialize)]
-    struct TestCase {
-        start: Start,
-        num_valid_moves: Option<usize>,
-        expected: Option<Vec<Expected>>,
+        Ok(())
     }
 
-    #[derive(Deserialize)]
-    struct Start {
-        description: Option<String>,
-        fen: String,
-    }
+    /// Apply every legal move of each test case's start position and compare the resulting
+    /// positions with the expected ones. Only the FEN fields selected by `comparator` are compared.
+    pub fn run_check_resulting_positions_test(
+        json_file_path: &str,
+        comparator: FenComparator,
+    ) -> Result<()> {
+        let suite = conformance::load(json_file_path)?;
+        assert_no_failures(conformance::check_resulting_positions(&suite, comparator)?);
 
-    #[derive(Deserialize)]
-    struct Expected {
-        #[serde(alias = "move")] // move is a reserved keyword; therefore, alias it
-        mov: String,
-        fen: String,
+        Ok(())
     }
+}
 
-    fn import_test(file_path: &str) -> Result<TestCases> {
-        let file = File::open(&file_path)?;
-        let mut buf_reader = BufReader::new(file);
-
-        let mut content = String::new();
-        buf_reader.read_to_string(&mut content)?;
-
-        let test_cases: TestCases = serde_json::from_str(&content)?;
+/// A global allocator for the unit tests that counts the allocations made by each thread, for
+/// checking that hot paths don't allocate.
+#[cfg(test)]
+pub mod alloc_counter {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
 
-        Ok(test_cases)
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
     }
 
-    fn str_to_bitfield<
-        T: Default
-            + From<char>
-            + std::ops::Add<Output = T>
-            + std::ops::Shl<usize, Output = T>
-            + std::ops::Add<T, Output = T>,
-    >(
-        bits: &str,
-    ) -> Result<T> {
-        let num_bits = bits.len();
-        let max_num_bits = std::mem::size_of_val(bits) * 8;
-        if num_bits > max_num_bits {
-            return Err(chess_error(&format!(
-                "Input is too long ({} > {})",
-                num_bits, max_num_bits
-            )));
-        }
-        if !bits.chars().all(|bit| bit == '0' || bit == '1') {
-            return Err(chess_error(&format!("All bits must be ones or zeros")));
+    struct CountingAllocator;
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            // The counter is gone while the thread is being torn down
+            let _ = ALLOCATIONS.try_with(|n| n.set(n.get() + 1));
+            unsafe { System.alloc(layout) }
         }
 
-        Ok(bits
-            .chars()
-            .enumerate()
-            .map(|c| T::from(c.1) << (num_bits - c.0))
-            .fold(T::default(), |a, b| a + b))
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
     }
 
-    pub fn run_check_num_moves_test(json_file_path: &str) -> Result<()> {
-        let test_cases = import_test(json_file_path)?;
-        let test_cases_description = test_cases
-            .description
-            .clone()
-            .unwrap_or(String::from("Unnamed test cases"));
-
-        for test_case in &test_cases.testcases {
-            let test_case_description = test_case
-                .start
-                .description
-                .clone()
-                .unwrap_or(String::from("Unnamed test case"));
-
-            let expected_num_moves = test_case.num_valid_moves.ok_or(chess_error(
-                "Test case file is missing the field \"num_moves\"",
-            ))?;
-
-            // Store a list of all possible moves generated by the SUT
-            let mut board: Board = fen::import(&test_case.start.fen)?;
-            let poss_moves = board.gen_moves();
-            let num_poss_moves = poss_moves.len();
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
 
-            assert_eq!(num_poss_moves, expected_num_moves, "Test case \"{test_case_description}\" of test suite \"{test_cases_description}\" failed");
-        }
-
-        Ok(())
+    /// Run `f` and return the number of heap allocations it made on the current thread. Tests run
+    /// in parallel threads, so allocations by other tests are not counted.
+    pub fn count_allocations(f: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.with(Cell::get);
+        f();
+        ALLOCATIONS.with(Cell::get) - before
     }
 }
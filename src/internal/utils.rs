@@ -1,3 +1,31 @@
-pub fn clamp_board_idx(val: i32) -> usize {
-    val.max(0).min(7) as usize
+/// Small seedable pseudo random number generator (xorshift64*).
+///
+/// Not suitable for anything that needs real randomness, but good enough for generating training
+/// material and fully reproducible from its seed.
+pub struct XorShift64(u64);
+
+impl XorShift64 {
+    pub fn new(seed: u64) -> Self {
+        // The all-zero state is a fixed point of xorshift; avoid it
+        XorShift64(if seed == 0 { 0x9e3779b97f4a7c15 } else { seed })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    /// Returns a number in the range `0..upper`.
+    pub fn next_below(&mut self, upper: usize) -> usize {
+        (self.next_u64() % upper as u64) as usize
+    }
+}
+
+/// 64-bit FNV-1a hash.
+pub fn fnv1a_64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
 }
@@ -0,0 +1,145 @@
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::board::{Board, Move};
+use crate::fen;
+use crate::piece::{BITS_BISHOP, BITS_BLACK, BITS_KNIGHT, BITS_QUEEN, BITS_ROOK};
+use crate::square::Square;
+
+/// Version of the JSON representation implemented by this module. Deserialization rejects
+/// documents with any other version.
+///
+/// The representation is:
+///
+/// * `Square`: a lowercase coordinate string, e.g. `"e4"`.
+/// * `Move`: an object `{ "from": "e7", "to": "e8", "promotion": "q" }`. The promotion is a
+///   lowercase piece letter and is left out for moves that aren't promotions. The annotations of
+///   generated moves are not included.
+/// * `Board`: an object `{ "schema": 1, "fen": "<FEN string>" }`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct BoardJson {
+    schema: u32,
+    fen: String,
+}
+
+impl Serialize for Square {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_str().to_lowercase())
+    }
+}
+
+impl<'de> Deserialize<'de> for Square {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Square::from(&s).map_err(D::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct MoveJson {
+    from: Square,
+    to: Square,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    promotion: Option<char>,
+}
+
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MoveJson {
+            from: self.from,
+            to: self.to,
+            promotion: self
+                .promotion
+                .map(|p_type| fen::piece_to_letter(BITS_BLACK | p_type)),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Move {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mv = MoveJson::deserialize(deserializer)?;
+        let promotion = match mv.promotion {
+            None => None,
+            Some('q') => Some(BITS_QUEEN),
+            Some('r') => Some(BITS_ROOK),
+            Some('b') => Some(BITS_BISHOP),
+            Some('n') => Some(BITS_KNIGHT),
+            Some(p) => return Err(D::Error::custom(format!("Invalid promotion piece '{}'", p))),
+        };
+
+        Ok(Move {
+            promotion,
+            ..Move::new(mv.from, mv.to)
+        })
+    }
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        BoardJson {
+            schema: SCHEMA_VERSION,
+            fen: fen::export(self),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let board = BoardJson::deserialize(deserializer)?;
+        if board.schema != SCHEMA_VERSION {
+            return Err(D::Error::custom(format!(
+                "Unsupported schema version {} (expected {})",
+                board.schema, SCHEMA_VERSION
+            )));
+        }
+
+        fen::import(&board.fen).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::square;
+
+    #[test]
+    fn square_and_move() {
+        let mv = Move::new(*square!("e2"), *square!("e4"));
+        let json = serde_json::to_string(&mv).unwrap();
+        assert_eq!(json, r#"{"from":"e2","to":"e4"}"#);
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), mv);
+
+        let mv = Move::with_promotion(*square!("e7"), *square!("e8"), BITS_KNIGHT);
+        let json = serde_json::to_string(&mv).unwrap();
+        assert_eq!(json, r#"{"from":"e7","to":"e8","promotion":"n"}"#);
+        assert_eq!(serde_json::from_str::<Move>(&json).unwrap(), mv);
+
+        let json = r#"{"from":"e7","to":"e8","promotion":"k"}"#;
+        assert!(serde_json::from_str::<Move>(json).is_err());
+
+        assert!(serde_json::from_str::<Square>(r#""i9""#).is_err());
+    }
+
+    #[test]
+    fn board_round_trip() {
+        let board = fen::import("r3k2r/8/8/8/8/8/8/R3K2R b Kq - 0 1").unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(
+            json,
+            r#"{"schema":1,"fen":"r3k2r/8/8/8/8/8/8/R3K2R b Kq - 0 0"}"#
+        );
+
+        let imported: Board = serde_json::from_str(&json).unwrap();
+        assert_eq!(fen::export(&imported), fen::export(&board));
+    }
+
+    #[test]
+    fn unknown_schema_is_rejected() {
+        let json = r#"{"schema":2,"fen":"8/8/8/8/8/8/8/8 w - - 0 1"}"#;
+        assert!(serde_json::from_str::<Board>(json).is_err());
+    }
+}
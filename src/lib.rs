@@ -1,9 +1,29 @@
 // Public modules
+pub mod analysis;
+pub mod bitboard;
 pub mod board;
+pub mod builder;
+pub mod chess960;
+#[cfg(any(test, feature = "conformance"))]
+pub mod conformance;
+pub mod editor;
+pub mod endgame;
 pub mod error;
+pub mod eval;
 pub mod fen;
+pub mod geometry;
+pub mod json;
+pub mod magic;
+pub mod move_list;
+pub mod perft;
 pub mod piece;
+pub mod position_filter;
+pub mod positions;
+pub mod prelude;
+pub mod search;
 pub mod square;
+pub mod training;
+pub mod zobrist;
 
 // Private modules
 mod internal;
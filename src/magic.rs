@@ -0,0 +1,323 @@
+//! Attacks of sliding pieces looked up with magic bitboards. The blockers on the lines a rook or
+//! bishop moves along are multiplied by a square-specific magic number, and the top bits of the
+//! product index a table of precomputed attack sets. The tables are filled on first use.
+
+use std::sync::OnceLock;
+
+use crate::bitboard::{self, Bitboard};
+use crate::square::Square;
+
+/// Attacks of a rook on `sq`, given the occupied squares. The attack set includes the first piece
+/// in each direction, whatever its color.
+pub fn rook_attacks(sq: &Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.lookup(&tables.rook[sq.index()], occupied)
+}
+
+/// Attacks of a bishop on `sq`, given the occupied squares. See `rook_attacks`.
+pub fn bishop_attacks(sq: &Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    tables.lookup(&tables.bishop[sq.index()], occupied)
+}
+
+/// Attacks of a queen on `sq`, given the occupied squares. See `rook_attacks`.
+pub fn queen_attacks(sq: &Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(sq, occupied) | bishop_attacks(sq, occupied)
+}
+
+const ROOK_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_DIRECTIONS: [(i32, i32); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+/// Lookup parameters of one square for one kind of slider.
+struct Magic {
+    /// The squares whose occupancy matters: the lines from the square, without the last square
+    /// of each line, since a piece there doesn't block anything
+    mask: Bitboard,
+    magic: u64,
+    /// 64 minus the number of squares in `mask`
+    shift: u32,
+    /// Start of the attack sets of the square in `Tables::attacks`
+    offset: usize,
+}
+
+struct Tables {
+    rook: Vec<Magic>,
+    bishop: Vec<Magic>,
+    attacks: Vec<Bitboard>,
+}
+
+impl Tables {
+    fn new() -> Self {
+        let mut attacks = Vec::new();
+        let mut fill = |magics: &[u64; 64], directions: &[(i32, i32)]| {
+            (0..64)
+                .map(|sq| {
+                    let mask = relevant_squares(sq, directions);
+                    let magic = Magic {
+                        mask,
+                        magic: magics[sq],
+                        shift: 64 - mask.count_ones(),
+                        offset: attacks.len(),
+                    };
+                    attacks.resize(attacks.len() + (1 << mask.count_ones()), bitboard::EMPTY);
+
+                    // Visit every subset of the mask (the Carry-Rippler trick)
+                    let mut blockers = bitboard::EMPTY;
+                    loop {
+                        let idx = magic.offset + index(&magic, blockers);
+                        attacks[idx] = slow_attacks(sq, blockers, directions);
+                        blockers = blockers.wrapping_sub(mask) & mask;
+                        if blockers == bitboard::EMPTY {
+                            break;
+                        }
+                    }
+
+                    magic
+                })
+                .collect()
+        };
+        let rook = fill(&ROOK_MAGICS, &ROOK_DIRECTIONS);
+        let bishop = fill(&BISHOP_MAGICS, &BISHOP_DIRECTIONS);
+
+        Tables {
+            rook,
+            bishop,
+            attacks,
+        }
+    }
+
+    fn lookup(&self, magic: &Magic, occupied: Bitboard) -> Bitboard {
+        self.attacks[magic.offset + index(magic, occupied)]
+    }
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(Tables::new)
+}
+
+fn index(magic: &Magic, occupied: Bitboard) -> usize {
+    ((occupied & magic.mask).wrapping_mul(magic.magic) >> magic.shift) as usize
+}
+
+/// The squares on the lines from square index `sq` in `directions`, except the last square of
+/// each line.
+fn relevant_squares(sq: usize, directions: &[(i32, i32)]) -> Bitboard {
+    let mut res = bitboard::EMPTY;
+    for (df, dr) in directions {
+        let (mut file, mut rank) = ((sq % 8) as i32 + df, (sq / 8) as i32 + dr);
+        while (0..8).contains(&(file + df)) && (0..8).contains(&(rank + dr)) {
+            res |= 1 << (rank * 8 + file);
+            file += df;
+            rank += dr;
+        }
+    }
+
+    res
+}
+
+/// Attacks of a slider on square index `sq` moving in `directions`, found by walking the lines
+/// until they hit a piece in `occupied` or the border.
+fn slow_attacks(sq: usize, occupied: Bitboard, directions: &[(i32, i32)]) -> Bitboard {
+    let mut res = bitboard::EMPTY;
+    for (df, dr) in directions {
+        let (mut file, mut rank) = ((sq % 8) as i32 + df, (sq / 8) as i32 + dr);
+        while (0..8).contains(&file) && (0..8).contains(&rank) {
+            res |= 1 << (rank * 8 + file);
+            if occupied & (1 << (rank * 8 + file)) != bitboard::EMPTY {
+                break;
+            }
+            file += df;
+            rank += dr;
+        }
+    }
+
+    res
+}
+
+// Found by trying sparse random numbers until one maps every subset of the mask to an index with
+// the right attack set (or to a shared index with the same attack set).
+const ROOK_MAGICS: [u64; 64] = [
+    0xa08001128022c004,
+    0x00c000a000300044,
+    0x4d80100120008228,
+    0x0680080081100084,
+    0x0080080004008002,
+    0x4900010004000208,
+    0x040018a810040a01,
+    0x820001004c002082,
+    0x0100802040008004,
+    0x025440002010044a,
+    0x4010801000200080,
+    0x0c05001000210088,
+    0x0000800800800400,
+    0x0004801600800400,
+    0x010c000402011028,
+    0x0082000093244402,
+    0x0040008000308040,
+    0x1020004030004000,
+    0x0200220010820840,
+    0xd00012000a004021,
+    0x0808008008800400,
+    0x08a0080104402010,
+    0x9000840002013028,
+    0x4000460001028244,
+    0x1480004040002000,
+    0x00a0401080200084,
+    0x4007004100142000,
+    0x1000102200400a00,
+    0x0109000500080090,
+    0x0120020080040080,
+    0x1004302400014208,
+    0x0e10240200008041,
+    0x0080002000400040,
+    0x0200200080804000,
+    0x0000408202002010,
+    0x0210041080800800,
+    0x1000800800800400,
+    0x8042001006000418,
+    0x0084020001010004,
+    0x00002412c2000981,
+    0x1880002000414000,
+    0x0838201008404000,
+    0x000b200500410012,
+    0x00d0100021010008,
+    0x0008000804008080,
+    0x0080040002008080,
+    0x1344420108040010,
+    0x4081000040810002,
+    0x28048a4022010200,
+    0x1000204086090200,
+    0xc020200080100080,
+    0x0800080010008280,
+    0x1004020408008080,
+    0x0882000804100200,
+    0x4006081002010400,
+    0x0080800100005880,
+    0x0004201082084102,
+    0x0100201081004009,
+    0x0101002004408991,
+    0x5001c50020300009,
+    0x020e000820100402,
+    0x0201000400080201,
+    0x0290421008009104,
+    0x4080002100440882,
+];
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0104102c48022040,
+    0x0022288800808044,
+    0x0a50112049000000,
+    0x1804040099000008,
+    0x000424202222c001,
+    0x0021300820001000,
+    0x40c1010110400022,
+    0x8800140401241021,
+    0x0222108401180e18,
+    0x0490500122109200,
+    0x0004040912220080,
+    0x0000024081004000,
+    0x0000020210040500,
+    0x04a040822020e280,
+    0x4800810409204802,
+    0x0022f20222010490,
+    0x28502041500200ac,
+    0x98a0402418008100,
+    0x0090010204101023,
+    0x2010200802802002,
+    0x0018101101400804,
+    0x0002811808040210,
+    0x0001003048088412,
+    0x4000221203011808,
+    0x20204872109a8818,
+    0x0282100820410200,
+    0x2000280810104040,
+    0x10300400804400c8,
+    0x7107010020104004,
+    0x0100880810821001,
+    0x0004025001080a04,
+    0x80d100448104008a,
+    0x1408020801102084,
+    0x0018044440030801,
+    0x4200139008420401,
+    0x4501110800040040,
+    0x00400080204a0020,
+    0x00900304c00a0048,
+    0x000c080e008880c0,
+    0x0400a10908060080,
+    0x0228a41040000800,
+    0x450100c804002010,
+    0x0331002110004107,
+    0x420800e018001501,
+    0x0081080104000042,
+    0x3040008889000080,
+    0x00101000810120a5,
+    0x80010400a2000080,
+    0x1400842402401c08,
+    0x0024212108200000,
+    0x0000488084411402,
+    0x4001000420a81010,
+    0x00a1410410441280,
+    0x8662410404042000,
+    0x94085918009c0486,
+    0x8202880809144200,
+    0x022080241202400c,
+    0x0244430121012081,
+    0x8580011100411000,
+    0x0100820e31841100,
+    0x0400010010020880,
+    0x308101a020012240,
+    0x1000a12004015040,
+    0x08900208010c0010,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::utils::XorShift64;
+    use crate::square::square;
+
+    #[test]
+    fn lookups_agree_with_walking_the_lines() {
+        let mut rng = XorShift64::new(7);
+        for _ in 0..2000 {
+            // Sparse, dense and everything in between
+            let occupied = match rng.next_below(3) {
+                0 => rng.next_u64() & rng.next_u64(),
+                1 => rng.next_u64(),
+                _ => rng.next_u64() | rng.next_u64(),
+            };
+            for idx in 0..64 {
+                let sq = Square::from_index(idx);
+                let rook = slow_attacks(idx, occupied, &ROOK_DIRECTIONS);
+                let bishop = slow_attacks(idx, occupied, &BISHOP_DIRECTIONS);
+                assert_eq!(rook_attacks(&sq, occupied), rook);
+                assert_eq!(bishop_attacks(&sq, occupied), bishop);
+                assert_eq!(queen_attacks(&sq, occupied), rook | bishop);
+            }
+        }
+    }
+
+    #[test]
+    fn attacks_stop_at_the_first_piece() {
+        let occupied = bitboard::from_square(square!("d6")) | bitboard::from_square(square!("b2"));
+        let squares = |bb| {
+            bitboard::squares(bb)
+                .map(|sq| sq.to_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            squares(rook_attacks(square!("d4"), occupied) & bitboard::FILE_D),
+            vec!["D1", "D2", "D3", "D5", "D6"]
+        );
+        assert_eq!(
+            squares(bishop_attacks(square!("d4"), occupied)),
+            vec!["G1", "B2", "F2", "C3", "E3", "C5", "E5", "B6", "F6", "A7", "G7", "H8"]
+        );
+        assert_eq!(
+            rook_attacks(square!("a1"), bitboard::EMPTY).count_ones(),
+            14
+        );
+        assert_eq!(queen_attacks(square!("d4"), bitboard::FULL).count_ones(), 8);
+    }
+}
@@ -0,0 +1,144 @@
+use std::ops::{Deref, DerefMut};
+use std::{array, fmt, iter};
+
+use crate::board::Move;
+use crate::square::Square;
+
+/// Capacity of a `MoveList`. No legal position has more than 218 legal moves.
+pub const MAX_MOVES: usize = 256;
+
+/// List of moves with a fixed capacity, stored inline instead of on the heap, so that move
+/// generation doesn't allocate. It derefs to a slice of the moves in the list.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Move; MAX_MOVES],
+    len: usize,
+}
+
+/// Filler for the unused entries of a `MoveList`.
+const NO_MOVE: Move = Move {
+    from: Square(0, 0),
+    to: Square(0, 0),
+    promotion: None,
+    moved: 0,
+    captured: 0,
+    flags: 0,
+};
+
+impl MoveList {
+    pub fn new() -> Self {
+        MoveList {
+            moves: [NO_MOVE; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    /// Add a move to the end of the list.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the list already holds `MAX_MOVES` moves.
+    pub fn push(&mut self, mv: Move) {
+        assert!(self.len < MAX_MOVES, "Move list is full");
+        self.moves[self.len] = mv;
+        self.len += 1;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Keep only the moves for which `f` returns true, in their original order.
+    pub fn retain(&mut self, mut f: impl FnMut(&Move) -> bool) {
+        let mut kept = 0;
+        for i in 0..self.len {
+            if f(&self.moves[i]) {
+                self.moves[kept] = self.moves[i];
+                kept += 1;
+            }
+        }
+        self.len = kept;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for MoveList {
+    type Target = [Move];
+
+    fn deref(&self) -> &[Move] {
+        &self.moves[..self.len]
+    }
+}
+
+impl DerefMut for MoveList {
+    fn deref_mut(&mut self) -> &mut [Move] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl fmt::Debug for MoveList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Move;
+    type IntoIter = iter::Take<array::IntoIter<Move, MAX_MOVES>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.moves.into_iter().take(self.len)
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Move;
+    type IntoIter = std::slice::Iter<'a, Move>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::square;
+    use itertools::Itertools;
+
+    #[test]
+    fn push_and_retain() {
+        let mut moves = MoveList::new();
+        assert!(moves.is_empty());
+
+        for to in ["e3", "e4", "d3"] {
+            moves.push(Move::new(*square!("e2"), Square::from(to).unwrap()));
+        }
+        assert_eq!(moves.len(), 3);
+        assert_eq!(moves[1], Move::new(*square!("e2"), *square!("e4")));
+
+        moves.retain(|mv| mv.to.0 == 4);
+        assert_eq!(
+            moves.iter().map(|mv| mv.to.to_str()).collect_vec(),
+            vec!["E3", "E4"]
+        );
+        assert_eq!(moves.clone().into_iter().count(), 2);
+
+        moves.clear();
+        assert!(moves.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn capacity_is_fixed() {
+        let mut moves = MoveList::new();
+        for _ in 0..=MAX_MOVES {
+            moves.push(NO_MOVE);
+        }
+    }
+}
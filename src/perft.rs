@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::board::{Board, Promotions};
+use crate::move_list::MoveList;
+
+/// Count the positions reached by all sequences of `depth` legal moves from `board`. Comparing
+/// the counts with known values is the standard way of testing a move generator.
+pub fn perft(board: &Board, depth: u32) -> u64 {
+    count(&mut board.clone(), depth)
+}
+
+/// Like `perft`, but the moves from `board` are shared out among `threads` threads, which count
+/// the positions after them in parallel. `std::thread::available_parallelism` is a good choice
+/// for the number of threads.
+pub fn perft_parallel(board: &Board, depth: u32, threads: usize) -> u64 {
+    if depth <= 1 {
+        return perft(board, depth);
+    }
+
+    let mut root_moves = MoveList::new();
+    board.gen_moves_into(&mut root_moves, Promotions::All);
+    // Threads take the next move to count as they finish, so those that get small subtrees
+    // don't sit idle
+    let next_move = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.max(1))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut board = board.without_history();
+                    let mut nodes = 0;
+                    while let Some(mv) = root_moves.get(next_move.fetch_add(1, Ordering::Relaxed)) {
+                        let undo = board.make_legal_move(mv);
+                        nodes += count(&mut board, depth - 1);
+                        board.unmake_move(undo);
+                    }
+                    nodes
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("Perft thread panicked"))
+            .sum()
+    })
+}
+
+fn count(board: &mut Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let mut moves = MoveList::new();
+    board.gen_moves_into(&mut moves, Promotions::All);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    moves
+        .iter()
+        .map(|mv| {
+            let undo = board.make_legal_move(mv);
+            let nodes = count(board, depth - 1);
+            board.unmake_move(undo);
+            nodes
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen;
+    use crate::positions;
+
+    #[test]
+    fn known_counts() -> crate::Result<()> {
+        let board = positions::startpos();
+        assert_eq!(perft(&board, 0), 1);
+        assert_eq!(perft(&board, 1), 20);
+        assert_eq!(perft(&board, 2), 400);
+        assert_eq!(perft(&board, 3), 8902);
+
+        let board = positions::kiwipete();
+        assert_eq!(perft(&board, 1), 48);
+        assert_eq!(perft(&board, 2), 2039);
+
+        let board = fen::import("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1")?;
+        assert_eq!(perft(&board, 4), 43238);
+        Ok(())
+    }
+
+    #[test]
+    fn parallel_counts_agree() {
+        let board = positions::kiwipete();
+        for threads in [1, 3, 8] {
+            assert_eq!(perft_parallel(&board, 3, threads), 97862);
+        }
+        assert_eq!(perft_parallel(&board, 1, 4), 48);
+        assert_eq!(perft_parallel(&board, 0, 4), 1);
+    }
+}
@@ -16,13 +16,13 @@
 ///    1 if piece has moved
 pub type Piece = u8;
 
-pub const BITS_NO_PIECE: Piece = 0 << 0;
-pub const BITS_PAWN: Piece = 1 << 0;
-pub const BITS_ROOK: Piece = 2 << 0;
-pub const BITS_KNIGHT: Piece = 3 << 0;
-pub const BITS_BISHOP: Piece = 4 << 0;
-pub const BITS_QUEEN: Piece = 5 << 0;
-pub const BITS_KING: Piece = 6 << 0;
+pub const BITS_NO_PIECE: Piece = 0;
+pub const BITS_PAWN: Piece = 1;
+pub const BITS_ROOK: Piece = 2;
+pub const BITS_KNIGHT: Piece = 3;
+pub const BITS_BISHOP: Piece = 4;
+pub const BITS_QUEEN: Piece = 5;
+pub const BITS_KING: Piece = 6;
 
 pub const BITS_WHITE: Piece = 0 << 3;
 pub const BITS_BLACK: Piece = 1 << 3;
@@ -43,6 +43,10 @@ pub fn piece_color(piece: Piece) -> Color {
     piece & (1 << 3)
 }
 
+pub fn opposite_color(color: Color) -> Color {
+    color ^ BITS_BLACK
+}
+
 pub fn is_piece(piece: Piece) -> bool {
     piece_type(piece) != BITS_NO_PIECE
 }
@@ -50,3 +54,22 @@ pub fn is_piece(piece: Piece) -> bool {
 pub fn has_moved(piece: Piece) -> bool {
     (piece & (1 << 4)) != 0
 }
+
+/// The Unicode chess symbol of a piece, e.g. '♔' for a white king.
+pub fn unicode_symbol(piece: Piece) -> char {
+    match (piece_type(piece), piece_color(piece)) {
+        (BITS_PAWN, BITS_WHITE) => '♙',
+        (BITS_ROOK, BITS_WHITE) => '♖',
+        (BITS_KNIGHT, BITS_WHITE) => '♘',
+        (BITS_BISHOP, BITS_WHITE) => '♗',
+        (BITS_QUEEN, BITS_WHITE) => '♕',
+        (BITS_KING, BITS_WHITE) => '♔',
+        (BITS_PAWN, _) => '♟',
+        (BITS_ROOK, _) => '♜',
+        (BITS_KNIGHT, _) => '♞',
+        (BITS_BISHOP, _) => '♝',
+        (BITS_QUEEN, _) => '♛',
+        (BITS_KING, _) => '♚',
+        _ => panic!("Invalid piece bits"),
+    }
+}
@@ -0,0 +1,178 @@
+use crate::board::Board;
+use crate::error::chess_error;
+use crate::Result;
+
+/// Upper bound on the number of hash functions of a filter. Sizes chosen by `PositionFilter::new`
+/// stay well below it; larger values in serialized filters are rejected.
+const MAX_HASHES: u32 = 64;
+
+/// Compact probabilistic set of positions (a Bloom filter), for checking quickly whether a position
+/// may be in a larger collection before looking it up there.
+///
+/// `contains` never gives false negatives, but may give false positives at roughly the rate the
+/// filter was created for, as long as no more positions than planned are inserted.
+///
+/// Positions are identified by `Board::stable_hash`, which doesn't change across versions of the
+/// crate, so a filter serialized with `to_bytes` stays valid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionFilter {
+    bits: Vec<u64>,
+    num_hashes: u32,
+}
+
+impl PositionFilter {
+    /// Create an empty filter sized for `expected_positions` positions with a false positive rate
+    /// of `false_positive_rate`, e.g. 0.01 for 1 %.
+    pub fn new(expected_positions: usize, false_positive_rate: f64) -> Self {
+        let n = expected_positions.max(1) as f64;
+        let p = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        // The standard optimal sizes: m = -n ln(p) / ln(2)^2 bits and k = m / n ln(2) hashes
+        let num_bits = (-n * p.ln() / (ln2 * ln2)).ceil().max(64.0) as usize;
+        // This is about log2(1/p) <= 30, or up to 64 ln(2) = 44 when the 64-bit minimum applies, so
+        // the clamp only guards the bound
+        let num_hashes = ((num_bits as f64 / n) * ln2)
+            .round()
+            .clamp(1.0, MAX_HASHES as f64) as u32;
+
+        PositionFilter {
+            bits: vec![0; num_bits.div_ceil(64)],
+            num_hashes,
+        }
+    }
+
+    pub fn insert(&mut self, board: &Board) {
+        self.insert_hash(board.stable_hash());
+    }
+
+    pub fn contains(&self, board: &Board) -> bool {
+        self.contains_hash(board.stable_hash())
+    }
+
+    /// Insert a position by its `Board::stable_hash`.
+    pub fn insert_hash(&mut self, hash: u64) {
+        for bit in bit_indices(hash, self.num_hashes, self.bits.len()) {
+            self.bits[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Check for a position by its `Board::stable_hash`.
+    pub fn contains_hash(&self, hash: u64) -> bool {
+        bit_indices(hash, self.num_hashes, self.bits.len())
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Serialize the filter. The encoding is the number of hashes as a 32-bit integer followed by
+    /// the bits in 64-bit words, all little-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.num_hashes.to_le_bytes().to_vec();
+        for word in &self.bits {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Read a filter serialized with `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 4 + 8 || !(bytes.len() - 4).is_multiple_of(8) {
+            return Err(chess_error("Invalid length of serialized position filter"));
+        }
+
+        let num_hashes = u32::from_le_bytes(bytes[..4].try_into()?);
+        if num_hashes == 0 || num_hashes > MAX_HASHES {
+            return Err(chess_error(&format!(
+                "Serialized position filter has {num_hashes} hash functions, expected 1 to {MAX_HASHES}"
+            )));
+        }
+        let bits = bytes[4..]
+            .chunks_exact(8)
+            .map(|word| Ok(u64::from_le_bytes(word.try_into()?)))
+            .collect::<Result<Vec<u64>>>()?;
+
+        Ok(PositionFilter { bits, num_hashes })
+    }
+}
+
+/// The bits of a hash in a filter of `num_words` 64-bit words, derived by double hashing from the
+/// two halves of a mixed hash.
+fn bit_indices(hash: u64, num_hashes: u32, num_words: usize) -> impl Iterator<Item = usize> {
+    let mixed = mix(hash);
+    let h1 = mixed & 0xffff_ffff;
+    // Never zero, so that the probes of a hash are spread out
+    let h2 = (mixed >> 32) | 1;
+    let num_bits = num_words as u64 * 64;
+
+    (0..num_hashes as u64).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+}
+
+/// The splitmix64 finalizer, which spreads the bits of the stable hash evenly over both halves.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::utils::XorShift64;
+    use crate::positions;
+
+    #[test]
+    fn inserted_positions_are_found() {
+        let mut filter = PositionFilter::new(100, 0.01);
+        assert!(!filter.contains(&Board::new()));
+
+        filter.insert(&Board::new());
+        filter.insert(&positions::kiwipete());
+        assert!(filter.contains(&Board::new()));
+        assert!(filter.contains(&positions::kiwipete()));
+        assert!(!filter.contains(&positions::lucena()));
+    }
+
+    #[test]
+    fn false_positive_rate() {
+        let mut rng = XorShift64::new(1);
+        let mut filter = PositionFilter::new(1000, 0.01);
+        for _ in 0..1000 {
+            filter.insert_hash(rng.next_u64());
+        }
+
+        let false_positives = (0..10_000)
+            .filter(|_| filter.contains_hash(rng.next_u64()))
+            .count();
+        assert!(false_positives < 300, "{false_positives} false positives");
+    }
+
+    #[test]
+    fn serialization() -> Result<()> {
+        let mut filter = PositionFilter::new(10, 0.01);
+        filter.insert(&Board::new());
+
+        let bytes = filter.to_bytes();
+        let restored = PositionFilter::from_bytes(&bytes)?;
+        assert_eq!(restored, filter);
+        assert!(restored.contains(&Board::new()));
+
+        assert!(PositionFilter::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(PositionFilter::from_bytes(&bytes[..4]).is_err());
+        assert!(PositionFilter::from_bytes(&[0; 12]).is_err());
+
+        let mut too_many_hashes = bytes.clone();
+        too_many_hashes[..4].copy_from_slice(&(MAX_HASHES + 1).to_le_bytes());
+        assert!(PositionFilter::from_bytes(&too_many_hashes).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn number_of_hashes_is_bounded() {
+        for expected_positions in [0, 1, 2, 1000] {
+            for false_positive_rate in [0.0, 1e-12, 0.01, 0.5, 1.0] {
+                let filter = PositionFilter::new(expected_positions, false_positive_rate);
+                assert!((1..=MAX_HASHES).contains(&filter.num_hashes));
+            }
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use crate::board::Board;
+use crate::fen;
+
+/// The standard starting position.
+pub const STARTPOS: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+/// Peter McKenzie's "Kiwipete", a middlegame position that exercises castling, en passant,
+/// promotions and pins. It is commonly used for perft testing.
+pub const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+/// A board without any pieces.
+pub const EMPTY: &str = "8/8/8/8/8/8/8/8 w - - 0 1";
+/// King and queen versus king.
+pub const KQK: &str = "8/8/8/4k3/8/8/8/3QK3 w - - 0 1";
+/// King and rook versus king.
+pub const KRK: &str = "8/8/8/4k3/8/8/8/R3K3 w - - 0 1";
+/// King and pawn versus king.
+pub const KPK: &str = "8/8/8/4k3/8/8/4P3/4K3 w - - 0 1";
+/// The Lucena position, the key winning position of rook and pawn versus rook endgames.
+pub const LUCENA: &str = "1K1k4/1P6/8/8/8/8/r7/2R5 w - - 0 1";
+
+pub fn startpos() -> Board {
+    from_const(STARTPOS)
+}
+
+pub fn kiwipete() -> Board {
+    from_const(KIWIPETE)
+}
+
+pub fn empty() -> Board {
+    from_const(EMPTY)
+}
+
+pub fn kqk() -> Board {
+    from_const(KQK)
+}
+
+pub fn krk() -> Board {
+    from_const(KRK)
+}
+
+pub fn kpk() -> Board {
+    from_const(KPK)
+}
+
+pub fn lucena() -> Board {
+    from_const(LUCENA)
+}
+
+fn from_const(fen: &str) -> Board {
+    fen::import(fen).expect("Built-in FEN is invalid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::{compare_fen, CMP_CASTLING, CMP_POS, CMP_SIDE_TO_MOVE};
+
+    #[test]
+    fn constructors_match_fens() {
+        let positions = [
+            (STARTPOS, startpos()),
+            (KIWIPETE, kiwipete()),
+            (EMPTY, empty()),
+            (KQK, kqk()),
+            (KRK, krk()),
+            (KPK, kpk()),
+            (LUCENA, lucena()),
+        ];
+
+        for (fen, board) in positions {
+            assert!(compare_fen(
+                fen,
+                &fen::export(&board),
+                CMP_POS | CMP_SIDE_TO_MOVE | CMP_CASTLING
+            )
+            .unwrap());
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! The commonly used types of the crate, for importing with `use chess::prelude::*;`.
+//!
+//! Items are only added here once their API is considered stable, so code that imports from the
+//! prelude is not affected when the modules they are defined in are reorganized.
+
+pub use crate::board::{Board, GameStatus, Move};
+pub use crate::builder::BoardBuilder;
+pub use crate::fen;
+pub use crate::piece::{
+    Color, Piece, BITS_BISHOP, BITS_BLACK, BITS_KING, BITS_KNIGHT, BITS_PAWN, BITS_QUEEN,
+    BITS_ROOK, BITS_WHITE,
+};
+pub use crate::search::SearchPosition;
+pub use crate::square::{square, Square};
+pub use crate::Result;
@@ -0,0 +1,193 @@
+use crate::board::{Board, Move, Undo};
+use crate::Result;
+
+/// Number of position hashes kept by `SearchPosition`. Positions further back than this are not
+/// considered for repetitions, which is fine since the fifty-move rule ends the game long before.
+const REPETITION_WINDOW: usize = 128;
+
+/// A single board with a stack of the moves made on it, for writing searches.
+///
+/// Moves are made with `push` and taken back with `pop`, which is cheaper than `Board::move_piece`
+/// since nothing but the undo information is recorded. Neither allocates as long as the search
+/// stays within `REPETITION_WINDOW` moves of the start. The Zobrist hashes of the positions on
+/// the path are kept in a ring buffer for repetition detection, seeded with the history of the
+/// board the search starts from.
+pub struct SearchPosition {
+    board: Board,
+    undos: Vec<Undo>,
+    hashes: [u64; REPETITION_WINDOW],
+    /// Index of the current position among all positions seen, including the history of the
+    /// starting board
+    ply: usize,
+}
+
+impl SearchPosition {
+    pub fn new(board: Board) -> Self {
+        let mut hashes = [0; REPETITION_WINDOW];
+        let history = &board.position_hashes;
+        let ply = history.len();
+
+        for (i, hash) in history
+            .iter()
+            .enumerate()
+            .skip(ply.saturating_sub(REPETITION_WINDOW))
+        {
+            hashes[i % REPETITION_WINDOW] = *hash;
+        }
+        hashes[ply % REPETITION_WINDOW] = board.zobrist_hash();
+
+        SearchPosition {
+            board,
+            undos: Vec::with_capacity(REPETITION_WINDOW),
+            hashes,
+            ply,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Number of moves made with `push` that haven't been taken back.
+    pub fn depth(&self) -> usize {
+        self.undos.len()
+    }
+
+    /// Make a move. Returns an error and leaves the position untouched if the move is not legal.
+    pub fn push(&mut self, mv: &Move) -> Result<()> {
+        let undo = self.board.make_move(mv)?;
+        self.undos.push(undo);
+        self.ply += 1;
+        self.hashes[self.ply % REPETITION_WINDOW] = self.board.zobrist_hash();
+
+        Ok(())
+    }
+
+    /// Take back the last move made with `push`. Returns the move that was taken back, or None if
+    /// there is no move to take back.
+    pub fn pop(&mut self) -> Option<Move> {
+        let undo = self.undos.pop()?;
+        let mv = undo.mv();
+        self.board.unmake_move(undo);
+        self.ply -= 1;
+
+        Some(mv)
+    }
+
+    /// Check if the current position occurred before, either in the search or in the history of
+    /// the starting board. Searches usually score such positions as draws right away, since the
+    /// side that can repeat can also repeat a third time.
+    ///
+    /// Only positions since the last capture or pawn move are considered, since those moves can't
+    /// be undone.
+    pub fn is_repetition(&self) -> bool {
+        let hash = self.hashes[self.ply % REPETITION_WINDOW];
+        let lookback = (self.board.halfmove_clock() as usize)
+            .min(self.ply)
+            .min(REPETITION_WINDOW - 1);
+
+        // Only positions with the same side to move can be equal
+        (2..=lookback)
+            .step_by(2)
+            .any(|back| self.hashes[(self.ply - back) % REPETITION_WINDOW] == hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen;
+    use crate::internal::test_utils::alloc_counter::count_allocations;
+    use crate::positions;
+    use crate::square::{square, Square};
+
+    fn play(pos: &mut SearchPosition, from: &str, to: &str) -> Result<()> {
+        pos.push(&Move::new(Square::from(from)?, Square::from(to)?))
+    }
+
+    #[test]
+    fn push_and_pop() -> Result<()> {
+        let mut pos = SearchPosition::new(Board::new());
+        let start = fen::export(pos.board());
+
+        play(&mut pos, "e2", "e4")?;
+        play(&mut pos, "e7", "e5")?;
+        assert_eq!(pos.depth(), 2);
+        assert!(play(&mut pos, "e4", "e5").is_err());
+        assert_eq!(pos.depth(), 2);
+
+        assert_eq!(pos.pop(), Some(Move::new(*square!("e7"), *square!("e5"))));
+        assert_eq!(pos.pop(), Some(Move::new(*square!("e2"), *square!("e4"))));
+        assert_eq!(pos.pop(), None);
+        assert_eq!(fen::export(pos.board()), start);
+        Ok(())
+    }
+
+    #[test]
+    fn repetitions_in_the_search() -> Result<()> {
+        let mut pos = SearchPosition::new(Board::new());
+
+        for (from, to) in [("g1", "f3"), ("g8", "f6"), ("f3", "g1")] {
+            play(&mut pos, from, to)?;
+            assert!(!pos.is_repetition());
+        }
+        play(&mut pos, "f6", "g8")?;
+        assert!(pos.is_repetition());
+
+        pos.pop();
+        assert!(!pos.is_repetition());
+        Ok(())
+    }
+
+    #[test]
+    fn repetitions_of_the_game_history() -> Result<()> {
+        let mut board = Board::new();
+        board.move_piece(square!("g1"), square!("f3"), None)?;
+        board.move_piece(square!("g8"), square!("f6"), None)?;
+
+        let mut pos = SearchPosition::new(board);
+        play(&mut pos, "f3", "g1")?;
+        assert!(!pos.is_repetition());
+        play(&mut pos, "f6", "g8")?;
+        assert!(pos.is_repetition());
+        Ok(())
+    }
+
+    #[test]
+    fn repetitions_from_an_imported_position() -> Result<()> {
+        // The board has no history, so only positions in the search can be repeated
+        let board = fen::import("4k3/8/8/8/8/8/8/R3K3 w - - 0 1")?;
+        let mut pos = SearchPosition::new(board);
+
+        for (from, to) in [("a1", "a2"), ("e8", "d8"), ("a2", "a1"), ("d8", "e8")] {
+            play(&mut pos, from, to)?;
+        }
+        assert!(pos.is_repetition());
+        Ok(())
+    }
+
+    #[test]
+    fn push_and_pop_do_not_allocate() -> Result<()> {
+        let mut pos = SearchPosition::new(positions::kiwipete());
+        let moves = pos.board().gen_moves();
+
+        for mv in &moves {
+            let mut res = Ok(());
+            assert_eq!(count_allocations(|| res = pos.push(mv)), 0);
+            res?;
+            assert_eq!(
+                count_allocations(|| {
+                    pos.is_repetition();
+                }),
+                0
+            );
+            assert_eq!(
+                count_allocations(|| {
+                    pos.pop();
+                }),
+                0
+            );
+        }
+        Ok(())
+    }
+}
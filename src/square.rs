@@ -1,6 +1,6 @@
 use crate::{error, Result};
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub struct Square(pub usize, pub usize);
 
 #[macro_export]
@@ -11,14 +11,14 @@ macro_rules! square {
         let u_str = $str.to_uppercase();
 
         let fst: u8 = u_str.as_bytes()[0];
-        assert!(fst >= 'A' as u8);
-        assert!(fst <= 'H' as u8);
-        let fst = (fst - 'A' as u8) as usize;
+        assert!(fst >= b'A');
+        assert!(fst <= b'H');
+        let fst = (fst - b'A') as usize;
 
         let snd: u8 = u_str.as_bytes()[1];
-        assert!(snd >= '1' as u8);
-        assert!(snd <= '8' as u8);
-        let snd = (snd - '1' as u8) as usize;
+        assert!(snd >= b'1');
+        assert!(snd <= b'8');
+        let snd = (snd - b'1') as usize;
 
         &Square(fst, snd)
     }};
@@ -34,13 +34,13 @@ impl Square {
 
         let u_str = s.to_uppercase();
         let fst = u_str.as_bytes()[0] as char;
-        if fst < 'A' || fst > 'H' {
+        if !('A'..='H').contains(&fst) {
             return Err(error::chess_error(&format!("Invalid rank: '{}'", fst)));
         }
         let fst = fst as usize - 'A' as usize;
 
         let snd = u_str.as_bytes()[1] as char;
-        if snd < '1' || snd > '8' {
+        if !('1'..='8').contains(&snd) {
             return Err(error::chess_error(&format!("Invalid file: '{}'", snd)));
         }
         let snd = snd as usize - '1' as usize;
@@ -48,8 +48,23 @@ impl Square {
         Ok(Square(fst, snd))
     }
 
+    /// Index of the square in the range 0..64, counting from A1 along the ranks (A1 = 0,
+    /// H1 = 7, A2 = 8, ..., H8 = 63).
+    pub fn index(&self) -> usize {
+        self.1 * 8 + self.0
+    }
+
+    pub fn from_index(idx: usize) -> Self {
+        assert!(idx < 64);
+        Square(idx % 8, idx / 8)
+    }
+
+    pub fn is_dark(&self) -> bool {
+        (self.0 + self.1).is_multiple_of(2)
+    }
+
     pub fn to_str(&self) -> String {
-        let file = (('A' as u8) + (self.0 as u8)) as char;
+        let file = (b'A' + (self.0 as u8)) as char;
         let rank = self.1 + 1;
 
         format!("{}{}", file, rank)
@@ -64,4 +79,13 @@ mod tests {
     fn square_macro_is_case_insensitive() {
         assert_eq!(square!("A1"), square!("a1"));
     }
+
+    #[test]
+    fn index_round_trip() {
+        assert_eq!(square!("a1").index(), 0);
+        assert_eq!(square!("h8").index(), 63);
+        for idx in 0..64 {
+            assert_eq!(Square::from_index(idx).index(), idx);
+        }
+    }
 }
@@ -0,0 +1,130 @@
+use crate::internal::utils::XorShift64;
+use crate::piece::{
+    piece_type, Piece, BITS_BISHOP, BITS_KING, BITS_KNIGHT, BITS_PAWN, BITS_QUEEN, BITS_ROOK,
+};
+use crate::square::Square;
+
+/// A single coordinate training exercise.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Question {
+    /// Is the given square light or dark?
+    SquareColor(Square),
+    /// A lone piece is shown on the board; name the square it stands on.
+    NameSquare(Piece, Square),
+}
+
+impl Question {
+    pub fn prompt(&self) -> String {
+        match self {
+            Question::SquareColor(sq) => format!("Is {} light or dark?", sq.to_str()),
+            Question::NameSquare(piece, _) => {
+                format!("Name the square of the {}.", piece_name(*piece))
+            }
+        }
+    }
+
+    /// The expected answer, formatted the same way it is accepted by `check`.
+    pub fn answer(&self) -> String {
+        match self {
+            Question::SquareColor(sq) if sq.is_dark() => String::from("dark"),
+            Question::SquareColor(_) => String::from("light"),
+            Question::NameSquare(_, sq) => sq.to_str(),
+        }
+    }
+
+    /// Check an answer given by the user. Leading/trailing whitespace and case are ignored.
+    pub fn check(&self, answer: &str) -> bool {
+        let answer = answer.trim();
+        match self {
+            Question::SquareColor(_) => answer.eq_ignore_ascii_case(&self.answer()),
+            Question::NameSquare(_, sq) => Square::from(answer).is_ok_and(|a| a == *sq),
+        }
+    }
+}
+
+/// Endless generator of coordinate training questions.
+///
+/// The generator is seeded, so the same seed always produces the same sequence of questions.
+pub struct Coordinates {
+    rng: XorShift64,
+}
+
+impl Coordinates {
+    pub fn new(seed: u64) -> Self {
+        Coordinates {
+            rng: XorShift64::new(seed),
+        }
+    }
+
+    pub fn random_square(&mut self) -> Square {
+        Square(self.rng.next_below(8), self.rng.next_below(8))
+    }
+
+    pub fn next_question(&mut self) -> Question {
+        let square = self.random_square();
+        if self.rng.next_below(2) == 0 {
+            Question::SquareColor(square)
+        } else {
+            let pieces = [
+                BITS_PAWN,
+                BITS_ROOK,
+                BITS_KNIGHT,
+                BITS_BISHOP,
+                BITS_QUEEN,
+                BITS_KING,
+            ];
+            Question::NameSquare(pieces[self.rng.next_below(pieces.len())], square)
+        }
+    }
+}
+
+impl Iterator for Coordinates {
+    type Item = Question;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_question())
+    }
+}
+
+fn piece_name(piece: Piece) -> &'static str {
+    match piece_type(piece) {
+        BITS_PAWN => "pawn",
+        BITS_ROOK => "rook",
+        BITS_KNIGHT => "knight",
+        BITS_BISHOP => "bishop",
+        BITS_QUEEN => "queen",
+        BITS_KING => "king",
+        _ => panic!("Invalid piece bits"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::square;
+
+    #[test]
+    fn same_seed_gives_same_questions() {
+        let a = Coordinates::new(42).take(20).collect::<Vec<_>>();
+        let b = Coordinates::new(42).take(20).collect::<Vec<_>>();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn square_colors() {
+        assert!(Question::SquareColor(*square!("a1")).check("dark"));
+        assert!(Question::SquareColor(*square!("h1")).check("Light"));
+        assert!(Question::SquareColor(*square!("e5")).check("dark"));
+        assert!(!Question::SquareColor(*square!("e4")).check("dark"));
+    }
+
+    #[test]
+    fn name_square() {
+        let question = Question::NameSquare(BITS_KNIGHT, *square!("f3"));
+        assert_eq!(question.prompt(), "Name the square of the knight.");
+        assert!(question.check("f3"));
+        assert!(question.check(" F3 "));
+        assert!(!question.check("f4"));
+        assert!(!question.check("nonsense"));
+    }
+}
@@ -0,0 +1,104 @@
+//! Random keys for Zobrist hashing (see `Board::zobrist_hash`). A hash is the XOR of the keys of
+//! the features of a position, so moves can update it by XORing the keys of the features they
+//! change. The keys are generated at compile time and are the same in every build, but unlike
+//! `Board::stable_hash`, they may change between versions of the crate.
+
+use crate::board::{
+    CastlingRights, CASTLE_BLACK_KINGSIDE, CASTLE_BLACK_QUEENSIDE, CASTLE_WHITE_KINGSIDE,
+    CASTLE_WHITE_QUEENSIDE,
+};
+use crate::piece::{piece_color, piece_type, Piece};
+use crate::square::Square;
+
+/// Keys for the 12 kinds of pieces on the 64 squares, then for the 4 castling rights, the 8 en
+/// passant files and Black to move.
+const KEYS: [u64; 12 * 64 + 4 + 8 + 1] = generate_keys();
+const CASTLING_OFFSET: usize = 12 * 64;
+const EN_PASSANT_OFFSET: usize = CASTLING_OFFSET + 4;
+
+/// Key XORed into the hash when Black is to move.
+pub const BLACK_TO_MOVE: u64 = KEYS[EN_PASSANT_OFFSET + 8];
+
+/// Key of `piece` standing on `sq`.
+///
+/// # Panics
+///
+/// Panics if `piece` is `BITS_NO_PIECE`.
+pub fn piece_key(piece: Piece, sq: &Square) -> u64 {
+    assert!(piece_type(piece) != 0, "Empty squares have no key");
+    let kind = (piece_type(piece) as usize - 1) * 2 + (piece_color(piece) >> 3) as usize;
+
+    KEYS[kind * 64 + sq.index()]
+}
+
+/// Combined key of the castling rights in `rights`.
+pub fn castling_key(rights: CastlingRights) -> u64 {
+    [
+        CASTLE_WHITE_KINGSIDE,
+        CASTLE_WHITE_QUEENSIDE,
+        CASTLE_BLACK_KINGSIDE,
+        CASTLE_BLACK_QUEENSIDE,
+    ]
+    .iter()
+    .enumerate()
+    .filter(|(_, right)| rights & **right != 0)
+    .fold(0, |key, (i, _)| key ^ KEYS[CASTLING_OFFSET + i])
+}
+
+/// Key of an en passant square on `file`.
+pub fn en_passant_key(file: usize) -> u64 {
+    KEYS[EN_PASSANT_OFFSET + file]
+}
+
+/// Fill the key table with the output of splitmix64 from a fixed seed.
+const fn generate_keys<const N: usize>() -> [u64; N] {
+    let mut keys = [0; N];
+    let mut state: u64 = 0x5a0b_4157_6368_6573;
+
+    let mut i = 0;
+    while i < N {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        keys[i] = z ^ (z >> 31);
+        i += 1;
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::CASTLE_NONE;
+    use crate::piece::{BITS_BLACK, BITS_KING, BITS_PAWN, BITS_WHITE};
+    use crate::square::square;
+    use std::collections::HashSet;
+
+    #[test]
+    fn keys_are_distinct() {
+        let keys: HashSet<u64> = KEYS.iter().copied().collect();
+        assert_eq!(keys.len(), KEYS.len());
+        assert!(!keys.contains(&0));
+    }
+
+    #[test]
+    fn keys_of_features() {
+        assert_ne!(
+            piece_key(BITS_WHITE | BITS_PAWN, square!("e4")),
+            piece_key(BITS_BLACK | BITS_PAWN, square!("e4"))
+        );
+        assert_eq!(
+            piece_key(BITS_BLACK | BITS_KING, square!("h8")),
+            KEYS[CASTLING_OFFSET - 1]
+        );
+
+        assert_eq!(castling_key(CASTLE_NONE), 0);
+        assert_eq!(
+            castling_key(CASTLE_WHITE_KINGSIDE | CASTLE_BLACK_QUEENSIDE),
+            castling_key(CASTLE_WHITE_KINGSIDE) ^ castling_key(CASTLE_BLACK_QUEENSIDE)
+        );
+        assert_ne!(en_passant_key(0), en_passant_key(7));
+    }
+}